@@ -0,0 +1,133 @@
+#![deny(clippy::expect_used, clippy::panic, clippy::unwrap_used)]
+#![warn(clippy::cargo, clippy::nursery, clippy::pedantic, clippy::try_err)]
+#![warn(clippy::todo, clippy::unimplemented, clippy::unreachable)]
+#![allow(clippy::module_name_repetitions, clippy::unused_async)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+#![allow(clippy::wildcard_imports)]
+
+use mr_manager::prelude::*;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    from: Option<Format>,
+    to: Format,
+    dir: String,
+    check: bool,
+}
+
+impl Args {
+    // every argument is a named flag, so a value can never be mistaken for another argument
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut from = None;
+        let mut to = None;
+        let mut dir = None;
+        let mut check = false;
+
+        while let Some(flag) = args.next() {
+            let mut value =
+                |name: &'static str| args.next().ok_or(Error::MissingValue(Value::Other(name)));
+
+            match flag.as_str() {
+                "--from" => from = Some(Format::try_from(value("Source format")?)?),
+                "--to" => to = Some(Format::try_from(value("Target format")?)?),
+                "--dir" => dir = Some(value("Directory")?),
+                "--check" => check = true,
+                _ => return Err(Error::InvalidValue(Value::Other("Argument"), flag)),
+            }
+        }
+
+        let Some(to) = to else {
+            return Err(Error::MissingValue(Value::Other("Target format")));
+        };
+        let dir = dir.unwrap_or_else(|| FileStorage::DIR.to_string());
+
+        Ok(Self {
+            from,
+            to,
+            dir,
+            check,
+        })
+    }
+}
+
+// converts every record under the data directory into the given format, validating each one first
+//
+// usage: convert --to <rmp|json> [--from <rmp|json>] [--dir <path>] [--check]
+fn main() -> Result<()> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    let (target, root) = (args.to, args.dir.as_str());
+    let dest = FileStorage::new(root, target);
+    let sources = args.from.map_or_else(|| Format::ALL.to_vec(), |f| vec![f]);
+
+    let mut converted = vec![];
+    let mut failed = 0;
+
+    for format in sources {
+        let source = FileStorage::new(root, format);
+
+        for record in records_in(&source, "")? {
+            match record.recode(target) {
+                Ok(raw) => converted.push((format, record, raw)),
+                Err(error) => {
+                    eprintln!("Invalid: {} - {error}", record.path());
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Other(
+            "Some records are invalid, so nothing was converted",
+        ));
+    }
+    if args.check {
+        println!("Validated {} records", converted.len());
+        return Ok(());
+    }
+
+    for (format, record, raw) in &converted {
+        dest.write(&record.dir, &record.key, raw)?;
+
+        if *format != target {
+            FileStorage::new(root, *format).remove(&record.dir, &record.key)?;
+        }
+    }
+
+    println!("Converted {} records to {}", converted.len(), target.ext());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn flags_parse_in_any_order() -> Result<()> {
+        let args = parse(&["--dir", "data", "--to", "json", "--from", "rmp", "--check"])?;
+
+        assert_eq!(args.from, Some(Format::Rmp));
+        assert_eq!(args.to, Format::Json);
+        assert_eq!(args.dir, "data");
+        assert!(args.check);
+
+        Ok(())
+    }
+
+    #[test]
+    fn target_is_required() {
+        assert!(parse(&["--dir", "data"]).is_err());
+        assert!(parse(&["--to"]).is_err());
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        assert!(parse(&["json"]).is_err());
+        assert!(parse(&["--to", "json", "--force"]).is_err());
+    }
+}
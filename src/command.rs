@@ -2,6 +2,7 @@ use crate::prelude::*;
 
 #[allow(clippy::use_self)]
 pub mod apply;
+pub mod audit;
 pub mod data;
 pub mod embed;
 pub mod help;
@@ -12,6 +13,79 @@ pub mod ping;
 pub mod poll;
 pub mod quote;
 pub mod role;
+pub mod settings;
+
+pub const AUTOCOMPLETE_LIMIT: usize = 25;
+
+pub const REGISTRY: Registry = Registry::new(&[
+    &apply::Command,
+    &audit::Command,
+    &data::Command,
+    &embed::Command,
+    &help::Command,
+    &offer::Command,
+    &oracle::Command,
+    &ping::Command,
+    &poll::Command,
+    &quote::Command,
+    &role::Command,
+    &settings::Command,
+]);
+
+pub fn migrate() -> Result<MigrationReport> {
+    let mut report = MigrationReport::default();
+
+    apply::migrate(&mut report)?;
+    audit::migrate(&mut report)?;
+    poll::migrate(&mut report)?;
+    role::migrate(&mut report)?;
+    settings::migrate(&mut report)?;
+    report.record(&Departed::new_req(()));
+
+    for req in Registration::query(()).reqs()? {
+        report.record(&req);
+    }
+
+    Ok(report)
+}
+
+fn __recode<T: Send + Sync + Versioned>(raw: &[u8], format: Format) -> Result<Vec<u8>> {
+    let value = Envelope::<T>::decode(raw)?;
+
+    Envelope::new(&value).seal_as(format)
+}
+
+// decodes a raw record as the type stored at its location, re-encoding it in the given format;
+// every stored type must be listed here, since backups and conversions refuse unknown locations
+pub fn recode(dir: &str, key: &str, raw: &[u8], format: Format) -> Result<Vec<u8>> {
+    let mut path = dir.split('/');
+    let root = path.next().unwrap_or_default();
+    let depth = path.count() + 1;
+
+    match (root, depth, key) {
+        (apply::NAME, 2, ".dat") => __recode::<apply::Config>(raw, format),
+        (apply::NAME, 2, _) => __recode::<apply::Form>(raw, format),
+        (audit::NAME, 2, _) => __recode::<audit::AuditLog>(raw, format),
+        (poll::NAME, 1, ".dat") => __recode::<poll::form::Active>(raw, format),
+        (poll::NAME, 2 | 3, _) => __recode::<poll::form::Form>(raw, format),
+        (role::NAME, 2, _) => __recode::<role::Selector>(raw, format),
+        (settings::NAME, 1, _) => __recode::<settings::Settings>(raw, format),
+        (Departed::DIR, 1, ".dat") => __recode::<Departed>(raw, format),
+        (Registration::DIR, 1, _) => __recode::<Registration>(raw, format),
+        _ => Err(Error::InvalidValue(
+            Value::Other("Unknown record location"),
+            format!("{dir}/{key}"),
+        )),
+    }
+}
+
+pub async fn purge(guild: GuildId) -> Result<()> {
+    apply::purge(guild)?;
+    audit::purge(guild)?;
+    poll::purge(guild).await?;
+    role::purge(guild)?;
+    settings::purge(guild)
+}
 
 fn __get_any<'c>(o: &'c [ResolvedOption<'c>], n: &'c str) -> Result<&'c ResolvedValue<'c>> {
     o.iter()
@@ -59,6 +133,56 @@ pub fn get_user<'c>(
     }
 }
 
+// autocompleted users and roles are taken as string options, so mentions are also accepted
+pub fn get_snowflake<'c>(o: &'c [ResolvedOption<'c>], n: &'c str, kind: Value) -> Result<u64> {
+    let raw = get_str(o, n)?;
+    let id = raw
+        .trim_start_matches("<@")
+        .trim_start_matches(['!', '&'])
+        .trim_end_matches('>');
+
+    match id.parse() {
+        Ok(id) if id != 0 => Ok(id),
+        _ => Err(Error::InvalidInput(kind, raw.to_string())),
+    }
+}
+
+pub fn get_focused(cmd: &CommandInteraction) -> Result<&str> {
+    cmd.data
+        .autocomplete()
+        .map(|o| o.name)
+        .ok_or(Error::MissingValue(Value::Data))
+}
+
+// responds with the choices whose names contain the focused option's current input
+pub async fn suggest(
+    http: &Http,
+    cmd: &CommandInteraction,
+    choices: Vec<(String, String)>,
+) -> Result<()> {
+    let input = cmd
+        .data
+        .autocomplete()
+        .map(|o| o.value.to_lowercase())
+        .unwrap_or_default();
+    let mut response = CreateAutocompleteResponse::new();
+
+    for (name, value) in choices
+        .into_iter()
+        .filter(|(name, _)| name.to_lowercase().contains(&input))
+        .take(AUTOCOMPLETE_LIMIT)
+    {
+        let name: String = name.chars().take(100).collect();
+
+        response = response.add_string_choice(name, value);
+    }
+
+    cmd.create_response(http, CreateInteractionResponse::Autocomplete(response))
+        .counted("create_interaction_response")
+        .await
+        .map_err(Error::from)
+}
+
 pub fn get_input_text<'c>(o: &'c [ActionRow], n: &'c str) -> Result<String> {
     for row in o {
         let Some(ActionRowComponent::InputText(input)) = row.components.first() else {
@@ -7,12 +7,14 @@ pub mod config;
 pub mod form;
 
 pub const NAME: &str = "apply";
+pub const DESCRIPTION: &str = "Manage guild applications";
 
 pub const SUB_CONFIG: &str = "config";
 pub const SUB_MODIFY: &str = "modify";
 pub const SUB_UPDATE: &str = "update";
 pub const SUB_REMOVE: &str = "remove";
 pub const SUB_VIEW: &str = "view";
+pub const SUB_LIST: &str = "list";
 
 pub const OPTION_TITLE: &str = "title";
 pub const OPTION_DESCRIPTION: &str = "description";
@@ -28,11 +30,13 @@ pub const OPTION_USER: &str = "user";
 pub const OPTION_STATUS: &str = "status";
 pub const OPTION_OVERWRITE: &str = "overwrite";
 
+pub const LIST_LIMIT: usize = 50;
+
 #[allow(clippy::too_many_lines)]
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::MODERATE_MEMBERS)
-        .description("Manage guild applications")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(
             CreateCommandOption::new(
@@ -228,10 +232,11 @@ pub fn new() -> CreateCommand {
             )
             .add_sub_option(
                 CreateCommandOption::new(
-                    CommandOptionType::User,
+                    CommandOptionType::String,
                     OPTION_USER,
                     "The guild member that submitted the application",
                 )
+                .set_autocomplete(true)
                 .required(true),
             )
             .add_sub_option(
@@ -268,10 +273,11 @@ pub fn new() -> CreateCommand {
             )
             .add_sub_option(
                 CreateCommandOption::new(
-                    CommandOptionType::User,
+                    CommandOptionType::String,
                     OPTION_USER,
                     "The guild member that submitted the application",
                 )
+                .set_autocomplete(true)
                 .required(true),
             ),
         )
@@ -283,13 +289,46 @@ pub fn new() -> CreateCommand {
             )
             .add_sub_option(
                 CreateCommandOption::new(
-                    CommandOptionType::User,
+                    CommandOptionType::String,
                     OPTION_USER,
                     "The guild member that submitted the application",
                 )
+                .set_autocomplete(true)
                 .required(true),
             ),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                SUB_LIST,
+                "Lists submitted applications",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    OPTION_STATUS,
+                    "The status of the listed applications",
+                )
+                .add_int_choice(Status::Pending.to_string(), Status::Pending as i32)
+                .add_int_choice(Status::Accepted.to_string(), Status::Accepted as i32)
+                .add_int_choice(Status::Denied.to_string(), Status::Denied as i32)
+                .add_int_choice(Status::Resend.to_string(), Status::Resend as i32),
+            ),
+        )
+}
+
+// a missing record here means the command was used before setup, or on a user who never applied
+fn __read_config(guild: GuildId) -> Result<Config> {
+    Config::read(guild).map_err(|error| match error {
+        error if error.is_not_found() => Error::Other("Applications have not been set up"),
+        error => error,
+    })
+}
+fn __read_form(guild: GuildId, user: UserId) -> Result<Form> {
+    Form::read((guild, user)).map_err(|error| match error {
+        error if error.is_not_found() => Error::Other("The user has not applied"),
+        error => error,
+    })
 }
 
 #[allow(clippy::too_many_lines)]
@@ -312,26 +351,26 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         .filter_map(Result::ok)
         .collect();
 
+        let _lock = Config::new_req(guild).lock().await?;
         let mut config = Config::new(
             get_channel(o, OPTION_CHANNEL)?.id,
             get_role(o, OPTION_ROLE)?.id,
             Content::new(title, description, thumbnail, questions),
         );
 
-        config.send(http, guild, cmd.channel_id).await?;
+        let deferral = Deferral::Reply { ephemeral: true };
+
+        cmd.defer_slow(http, deferral, config.send(http, guild, cmd.channel_id))
+            .await?;
 
         let embed = CreateEmbed::new()
             .color(BOT_COLOR)
             .title("Configured applications!");
-        let message = CreateInteractionResponseMessage::new()
-            .embed(embed)
-            .ephemeral(true);
 
-        cmd.create_response(http, CreateInteractionResponse::Message(message))
-            .await
-            .map_err(Error::from)
+        cmd.reply(http, embed, true).await
     } else if let Ok(o) = get_subcommand(o, SUB_MODIFY) {
-        let mut config = Config::read(guild)?;
+        let _lock = Config::new_req(guild).lock().await?;
+        let mut config = __read_config(guild)?;
         let mut update = false;
 
         if let Ok(title) = get_str(o, OPTION_TITLE) {
@@ -375,7 +414,10 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         }
 
         if update {
-            config.send(http, guild, cmd.channel_id).await?;
+            let deferral = Deferral::Reply { ephemeral: true };
+
+            cmd.defer_slow(http, deferral, config.send(http, guild, cmd.channel_id))
+                .await?;
         } else {
             config.try_write(())?;
         }
@@ -383,21 +425,17 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         let embed = CreateEmbed::new()
             .color(BOT_COLOR)
             .title("Updated application configuration!");
-        let message = CreateInteractionResponseMessage::new()
-            .embed(embed)
-            .ephemeral(true);
 
-        cmd.create_response(http, CreateInteractionResponse::Message(message))
-            .await
-            .map_err(Error::from)
+        cmd.reply(http, embed, true).await
     } else if let Ok(o) = get_subcommand(o, SUB_UPDATE) {
-        let (user, _) = get_user(o, OPTION_USER)?;
+        let user = UserId::new(get_snowflake(o, OPTION_USER, Value::User)?);
         let status = Status::try_from(get_i64(o, OPTION_STATUS)?)?;
         let reason = get_str(o, OPTION_REASON).ok();
         let overwrite = get_bool(o, OPTION_OVERWRITE).unwrap_or(false);
 
-        let config = Config::read(guild)?;
-        let mut form = Form::read((guild, user.id))?;
+        let _lock = Form::new_req((guild, user)).lock().await?;
+        let config = __read_config(guild)?;
+        let mut form = __read_form(guild, user)?;
 
         if form.status == status {
             return Err(Error::Other("The application already has this status"));
@@ -406,35 +444,60 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             return Err(Error::Other("The user's application is already finalized"));
         }
 
-        form.update(http, guild, config.role, status, reason)
-            .await?;
+        let deferral = Deferral::Reply { ephemeral: true };
+
+        cmd.defer_slow(http, deferral, async {
+            form.update(http, guild, config.role, status, reason)
+                .await?;
+
+            if let Some(action) = audit::Action::from_status(status) {
+                let entry = audit::Entry::new(cmd.user.id, action, audit::Target::User(user));
+
+                audit::record(guild, entry.reason(reason)).await;
+            }
+
+            Ok(())
+        })
+        .await?;
 
         let embed = CreateEmbed::new()
             .color(BOT_COLOR)
             .title("Updated user application!");
-        let message = CreateInteractionResponseMessage::new()
-            .embed(embed)
-            .ephemeral(true);
 
-        cmd.create_response(http, CreateInteractionResponse::Message(message))
-            .await
-            .map_err(Error::from)
+        cmd.reply(http, embed, true).await
     } else if let Ok(o) = get_subcommand(o, SUB_REMOVE) {
-        let (user, _) = get_user(o, OPTION_USER)?;
-        let config = Config::read(guild)?;
+        let user = UserId::new(get_snowflake(o, OPTION_USER, Value::User)?);
+        let _lock = Form::new_req((guild, user)).lock().await?;
+        let config = __read_config(guild)?;
 
-        let form = Form::read((guild, user.id))?;
-        let mut member = guild.member(http, user.id).await?;
+        let form = __read_form(guild, user)?;
+        let mut member = guild.member(http, user).counted("get_member").await?;
 
         if let Ok(anchor) = form.anchor() {
-            anchor.to_message(http).await?.delete(http).await?;
+            anchor
+                .to_message(http)
+                .await?
+                .delete(http)
+                .counted("delete_message")
+                .await?;
         }
         if member.roles.contains(&config.role) {
-            member.remove_role(http, config.role).await?;
+            member
+                .remove_role(http, config.role)
+                .counted("remove_member_role")
+                .await?;
         }
 
         form.try_remove(())?;
 
+        let entry = audit::Entry::new(
+            cmd.user.id,
+            audit::Action::ApplicationRemoved,
+            audit::Target::User(user),
+        );
+
+        audit::record(guild, entry).await;
+
         let embed = CreateEmbed::new()
             .color(BOT_COLOR)
             .title("Removed user application!");
@@ -443,11 +506,12 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if let Ok(o) = get_subcommand(o, SUB_VIEW) {
-        let (user, _) = get_user(o, OPTION_USER)?;
-        let form = Form::read((guild, user.id))?;
+        let user = UserId::new(get_snowflake(o, OPTION_USER, Value::User)?);
+        let form = __read_form(guild, user)?;
 
         let embed = form.as_embed(http, guild).await?;
         let message = CreateInteractionResponseMessage::new()
@@ -455,12 +519,81 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else if let Ok(o) = get_subcommand(o, SUB_LIST) {
+        let status = get_i64(o, OPTION_STATUS).map_or(Ok(Status::Pending), Status::try_from)?;
+        let forms = Form::query(guild)
+            .filter(move |f| f.status == status)
+            .sort_by_key(|f| f.anchor().map(|a| a.message).ok())
+            .read()?;
+
+        let description = if forms.is_empty() {
+            "*No applications found...*".to_string()
+        } else {
+            forms
+                .iter()
+                .take(LIST_LIMIT)
+                .fold(String::new(), |s, (_, f)| {
+                    format!("{s}<@{}> - {}\n", f.user, f.created_string())
+                })
+        };
+        let mut embed = CreateEmbed::new()
+            .color(BOT_COLOR)
+            .description(description)
+            .title(format!("Applications: {status}"));
+
+        if forms.len() > LIST_LIMIT {
+            let text = format!("Showing {LIST_LIMIT} of {}", forms.len());
+
+            embed = embed.footer(CreateEmbedFooter::new(text));
+        }
+        let message = CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else {
         Err(Error::InvalidId(Value::Command, cmd.data.name.clone()))
     }
 }
+pub async fn run_autocomplete(ctx: &Context, cmd: &CommandInteraction) -> Result<()> {
+    let guild = cmd.guild_id.ok_or(Error::MissingId(Value::Guild))?;
+    let focused = get_focused(cmd)?;
+
+    if focused != OPTION_USER {
+        return Err(Error::InvalidValue(Value::Data, focused.to_string()));
+    }
+
+    // forms are keyed by user, so suggestions only need the keys and whatever the cache knows
+    let users: Vec<UserId> = Form::query(guild)
+        .reqs()?
+        .iter()
+        .filter_map(|r| r.key().parse().ok())
+        .map(UserId::new)
+        .collect();
+    let choices = {
+        let cached = ctx.cache.guild(guild);
+
+        users
+            .into_iter()
+            .map(|user| {
+                let name = cached
+                    .as_ref()
+                    .and_then(|g| g.members.get(&user))
+                    .map_or_else(|| user.to_string(), |m| m.display_name().to_string());
+
+                (name, user.to_string())
+            })
+            .collect()
+    };
+
+    suggest(&ctx.http, cmd, choices).await
+}
 pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
     let custom_id = CustomId::try_from(cpn.data.custom_id.as_str())?;
     let guild = cpn.guild_id.ok_or(Error::MissingId(Value::Guild))?;
@@ -479,11 +612,12 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             let modal = Config::read(guild)?.as_modal(());
 
             cpn.create_response(http, CreateInteractionResponse::Modal(modal))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
         BUTTON_ABOUT => {
-            let user = http.get_current_user().await?;
+            let user = http.get_current_user().counted("get_current_user").await?;
             let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
             let embed = CreateEmbed::new()
                 .author(author)
@@ -496,6 +630,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
                 .ephemeral(true);
 
             cpn.create_response(http, CreateInteractionResponse::Message(message))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -518,6 +653,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             let modal = Form::read((guild, user))?.as_modal(status);
 
             cpn.create_response(http, CreateInteractionResponse::Modal(modal))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -537,13 +673,17 @@ pub async fn run_modal(http: &Http, mdl: &ModalInteraction) -> Result<()> {
                 .filter_map(|n| get_input_text(o, &n.to_string()).ok())
                 .collect();
 
+            let _lock = Form::new_req((guild, mdl.user.id)).lock().await?;
             let mut form = Form::new(mdl.user.id, answers);
 
-            form.send(http, guild, config.channel).await?;
+            mdl.defer_slow(
+                http,
+                Deferral::Update,
+                form.send(http, guild, config.channel),
+            )
+            .await?;
 
-            mdl.create_response(http, CreateInteractionResponse::Acknowledge)
-                .await
-                .map_err(Error::from)
+            mdl.acknowledge(http).await
         }
         MODAL_UPDATE => {
             let mut args = custom_id.args.iter();
@@ -565,15 +705,98 @@ pub async fn run_modal(http: &Http, mdl: &ModalInteraction) -> Result<()> {
             let status = Status::try_from(status)?;
 
             let reason = get_input_text(o, OPTION_REASON).ok();
+            let _lock = Form::new_req((guild, user)).lock().await?;
             let mut form = Form::read((guild, user))?;
 
-            form.update(http, guild, config.role, status, reason)
-                .await?;
+            mdl.defer_slow(http, Deferral::Update, async {
+                form.update(http, guild, config.role, status, reason.clone())
+                    .await?;
 
-            mdl.create_response(http, CreateInteractionResponse::Acknowledge)
-                .await
-                .map_err(Error::from)
+                if let Some(action) = audit::Action::from_status(status) {
+                    let entry = audit::Entry::new(mdl.user.id, action, audit::Target::User(user));
+
+                    audit::record(guild, entry.reason(reason)).await;
+                }
+
+                Ok(())
+            })
+            .await?;
+
+            mdl.acknowledge(http).await
         }
         _ => Err(Error::InvalidId(Value::Modal, custom_id.name)),
     }
 }
+
+pub fn migrate(report: &mut MigrationReport) -> Result<()> {
+    for dir in ReqDir::<Config>::new(NAME).dirs()? {
+        report.record(&dir.req(".dat"));
+    }
+    for req in Form::query(()).reqs()? {
+        report.record(&req);
+    }
+
+    Ok(())
+}
+
+pub fn purge(guild: GuildId) -> Result<()> {
+    storage().clear(&format!("{NAME}/{guild}"))
+}
+
+pub fn export(user: UserId, export: &mut data::Export) -> Result<()> {
+    let (forms, failed) = Form::query(())
+        .filter(move |f| f.user == user)
+        .read_with_errors()?;
+
+    for (req, form) in forms {
+        export.push(&req, "application", &form)?;
+    }
+
+    export.skip(&failed);
+
+    Ok(())
+}
+
+pub async fn forget(user: UserId) -> Result<usize> {
+    let mut count = 0;
+
+    for req in Form::query(()).reqs()? {
+        let _lock = req.lock().await?;
+
+        // read under the lock, so that the record cannot change between the check and removal
+        if req.read().is_ok_and(|f| f.user == user) {
+            req.remove()?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+    async fn run_autocomplete(&self, ctx: &Context, cmd: &CommandInteraction) -> Result<()> {
+        run_autocomplete(ctx, cmd).await
+    }
+    async fn run_component(&self, http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+        run_component(http, cpn).await
+    }
+    async fn run_modal(&self, http: &Http, mdl: &ModalInteraction) -> Result<()> {
+        run_modal(http, mdl).await
+    }
+}
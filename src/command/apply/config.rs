@@ -56,11 +56,14 @@ impl Config {
 
         if let Some(anchor) = self.anchor {
             if let Ok(message) = anchor.to_message(http).await {
-                message.delete(http).await?;
+                message.delete(http).counted("delete_message").await?;
             }
         }
 
-        let message = channel.send_message(http, builder).await?;
+        let message = channel
+            .send_message(http, builder)
+            .counted("create_message")
+            .await?;
         self.anchor = Some(Anchor::try_from((guild, message))?);
         self.try_write(())
     }
@@ -72,6 +75,10 @@ impl Anchored for Config {
     }
 }
 
+impl Versioned for Config {
+    const MIGRATIONS: &'static [Migration] = &[legacy::<Self>];
+}
+
 impl NewReq<GuildId> for Config {
     fn new_req(guild: GuildId) -> Req<Self> {
         Req::new(format!("{NAME}/{guild}"), ".dat")
@@ -93,7 +100,7 @@ impl AsReq<GuildId> for Config {
 #[async_trait]
 impl AsEmbedAsync<GuildId> for Config {
     async fn as_embed(&self, http: &Http, guild: GuildId) -> Result<CreateEmbed> {
-        let guild = http.get_guild(guild).await?;
+        let guild = http.get_guild(guild).counted("get_guild").await?;
         let footer = CreateEmbedFooter::new(format!("Questions: {}", self.content.questions.len()));
         let mut author = CreateEmbedAuthor::new(&guild.name);
 
@@ -91,16 +91,20 @@ impl Form {
             builder = builder.button(button);
         }
 
-        let message = channel.send_message(http, builder).await?;
+        let message = channel
+            .send_message(http, builder)
+            .counted("create_message")
+            .await?;
 
         self.anchor = Some(Anchor::try_from((guild, message))?);
         self.try_write(())
     }
     #[allow(clippy::match_same_arms, clippy::match_wildcard_for_single_variants)] // prevents false positives, intended
     pub async fn notify(&self, http: &Http, guild: GuildId) -> Result<()> {
-        let guild = http.get_guild(guild).await?;
+        let guild = http.get_guild(guild).counted("get_guild").await?;
 
-        let Ok(channel) = self.user.create_dm_channel(http).await else {
+        let channel = self.user.create_dm_channel(http);
+        let Ok(channel) = channel.counted("create_private_channel").await else {
             return Ok(())
         };
 
@@ -135,6 +139,7 @@ impl Form {
 
         channel
             .send_message(http, CreateMessage::new().embed(embed))
+            .counted("create_message")
             .await?;
 
         Ok(())
@@ -147,16 +152,22 @@ impl Form {
         status: Status,
         reason: Option<impl Send + Sync + Into<String>>,
     ) -> Result<()> {
-        let mut member = guild.member(http, self.user).await?;
+        let mut member = guild.member(http, self.user).counted("get_member").await?;
 
         self.status = status;
         self.reason = reason.map(Into::into);
         self.try_write(())?;
 
         if status == Status::Accepted {
-            member.add_role(http, role).await?;
+            member
+                .add_role(http, role)
+                .counted("add_member_role")
+                .await?;
         } else {
-            member.remove_role(http, role).await?;
+            member
+                .remove_role(http, role)
+                .counted("remove_member_role")
+                .await?;
         }
 
         if let Some(anchor) = self.anchor {
@@ -167,7 +178,7 @@ impl Form {
                 builder = builder.button(button);
             }
 
-            message.edit(http, builder).await?;
+            message.edit(http, builder).counted("edit_message").await?;
         }
 
         self.notify(http, guild).await
@@ -180,12 +191,36 @@ impl Anchored for Form {
     }
 }
 
+impl Versioned for Form {
+    const MIGRATIONS: &'static [Migration] = &[legacy::<Self>];
+}
+
 impl NewReq<(GuildId, UserId)> for Form {
     fn new_req((guild, user): (GuildId, UserId)) -> Req<Self> {
         Req::new(format!("{NAME}/{guild}"), user)
     }
 }
 
+impl NewReqDir<()> for Form {
+    fn new_req_dir(_: ()) -> ReqDir<Self> {
+        ReqDir::new(NAME)
+    }
+
+    fn query(_: ()) -> Query<Self> {
+        Self::new_req_dir(()).query().deep().exclude(".dat")
+    }
+}
+
+impl NewReqDir<GuildId> for Form {
+    fn new_req_dir(guild: GuildId) -> ReqDir<Self> {
+        ReqDir::new(format!("{NAME}/{guild}"))
+    }
+
+    fn query(guild: GuildId) -> Query<Self> {
+        Self::new_req_dir(guild).query().exclude(".dat")
+    }
+}
+
 impl TryAsReq<()> for Form {
     fn try_as_req(&self, _: ()) -> Result<Req<Self>> {
         Ok(Self::new_req((self.anchor()?.guild, self.user)))
@@ -202,7 +237,7 @@ impl AsReq<GuildId> for Form {
 impl AsEmbedAsync<GuildId> for Form {
     async fn as_embed(&self, http: &Http, guild: GuildId) -> Result<CreateEmbed> {
         let config = Config::read(guild)?;
-        let user = http.get_user(self.user).await?;
+        let user = http.get_user(self.user).counted("get_user").await?;
 
         let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
         let color = user.accent_colour.unwrap_or(BOT_COLOR);
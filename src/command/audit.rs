@@ -0,0 +1,426 @@
+use crate::prelude::*;
+
+pub const NAME: &str = "audit";
+pub const DESCRIPTION: &str = "Displays the guild's moderation audit log";
+
+pub const BUTTON_LAST: &str = formatcp!("{NAME}_last");
+pub const BUTTON_NEXT: &str = formatcp!("{NAME}_next");
+pub const PAGER: Pager = Pager::new(BUTTON_LAST, BUTTON_NEXT);
+
+pub const OPTION_USER: &str = "user";
+pub const OPTION_ACTION: &str = "action";
+
+pub const PAGE_SIZE: usize = 10;
+// keeps a full page under the 4096 character embed description limit
+pub const LINE_LIMIT: usize = 400;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    ApplicationAccepted = 0,
+    ApplicationDenied = 1,
+    ApplicationResend = 2,
+    ApplicationRemoved = 3,
+    SelectorAdded = 4,
+    SelectorRemoved = 5,
+    SelectorSent = 6,
+    PollClosed = 7,
+}
+
+impl Action {
+    pub const ALL: [Self; 8] = [
+        Self::ApplicationAccepted,
+        Self::ApplicationDenied,
+        Self::ApplicationResend,
+        Self::ApplicationRemoved,
+        Self::SelectorAdded,
+        Self::SelectorRemoved,
+        Self::SelectorSent,
+        Self::PollClosed,
+    ];
+
+    pub const fn from_status(status: apply::Status) -> Option<Self> {
+        match status {
+            apply::Status::Pending => None,
+            apply::Status::Accepted => Some(Self::ApplicationAccepted),
+            apply::Status::Denied => Some(Self::ApplicationDenied),
+            apply::Status::Resend => Some(Self::ApplicationResend),
+        }
+    }
+}
+
+impl TryFrom<i64> for Action {
+    type Error = Error;
+
+    fn try_from(value: i64) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|a| *a as i64 == value)
+            .ok_or_else(|| Error::InvalidValue(Value::Data, value.to_string()))
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::ApplicationAccepted => "Accepted application",
+            Self::ApplicationDenied => "Denied application",
+            Self::ApplicationResend => "Requested application resend",
+            Self::ApplicationRemoved => "Removed application",
+            Self::SelectorAdded => "Added role selector",
+            Self::SelectorRemoved => "Removed role selector",
+            Self::SelectorSent => "Sent role selectors",
+            Self::PollClosed => "Closed poll",
+        };
+
+        write!(f, "{text}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    User(UserId),
+    Role(RoleId),
+    Roles(Vec<RoleId>),
+    Message(Anchor),
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(user) => write!(f, "<@{user}>"),
+            Self::Role(role) => write!(f, "<@&{role}>"),
+            Self::Roles(roles) => {
+                let roles: Vec<_> = roles.iter().map(|r| format!("<@&{r}>")).collect();
+
+                write!(f, "{}", roles.join(", "))
+            }
+            Self::Message(anchor) => write!(f, "{anchor}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    pub actor: UserId,
+    pub action: Action,
+    pub target: Target,
+    pub reason: Option<String>,
+}
+
+impl Entry {
+    pub fn new(actor: UserId, action: Action, target: Target) -> Self {
+        Self {
+            time: Utc::now(),
+            actor,
+            action,
+            target,
+            reason: None,
+        }
+    }
+    pub fn reason(mut self, reason: Option<impl Into<String>>) -> Self {
+        self.reason = reason.map(Into::into);
+        self
+    }
+
+    // whether the user either performed the action or was its subject
+    pub fn involves(&self, user: UserId) -> bool {
+        self.actor == user || self.target == Target::User(user)
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<t:{}:f> <@{}> **{}** {}",
+            self.time.timestamp(),
+            self.actor,
+            self.action,
+            self.target
+        )?;
+
+        if let Some(reason) = &self.reason {
+            write!(f, "\n> {reason}")?;
+        }
+
+        Ok(())
+    }
+}
+
+// entries are appended to one record per guild and month, so no single record grows unbounded
+#[repr(transparent)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AuditLog(pub Vec<Entry>);
+
+impl Versioned for AuditLog {}
+
+impl NewReq<(GuildId, DateTime<Utc>)> for AuditLog {
+    fn new_req((guild, time): (GuildId, DateTime<Utc>)) -> Req<Self> {
+        Req::new(format!("{NAME}/{guild}"), time.format("%Y%m"))
+    }
+}
+
+impl NewReqDir<()> for AuditLog {
+    fn new_req_dir(_: ()) -> ReqDir<Self> {
+        ReqDir::new(NAME)
+    }
+
+    fn query(_: ()) -> Query<Self> {
+        Self::new_req_dir(()).query().deep()
+    }
+}
+
+impl NewReqDir<GuildId> for AuditLog {
+    fn new_req_dir(guild: GuildId) -> ReqDir<Self> {
+        ReqDir::new(format!("{NAME}/{guild}"))
+    }
+}
+
+async fn __record(guild: GuildId, entry: Entry) -> Result<()> {
+    let req = AuditLog::new_req((guild, entry.time));
+    let _lock = req.lock().await?;
+    let mut log = match req.read() {
+        Ok(log) => log,
+        Err(error) if error.is_not_found() => AuditLog::default(),
+        Err(error) => return Err(error),
+    };
+
+    log.0.push(entry);
+    req.write(&log)
+}
+
+// the action has already happened by the time it is recorded, so a failure is only logged
+pub async fn record(guild: GuildId, entry: Entry) {
+    let action = entry.action;
+
+    if let (Err(error), Some(logger)) = (__record(guild, entry).await, logger()) {
+        logger
+            .target(NAME)
+            .guild(guild)
+            .warn(format!("Recording failed: {action} - {error}"))
+            .ok();
+    }
+}
+
+fn __clip(mut line: String) -> String {
+    if line.len() > LINE_LIMIT {
+        let end = (0..=LINE_LIMIT - 3)
+            .rev()
+            .find(|i| line.is_char_boundary(*i))
+            .unwrap_or_default();
+
+        line.truncate(end);
+        line.push_str("...");
+    }
+
+    line
+}
+
+// every matching entry in the guild, newest first, along with the number of logs that could not
+// be read; each of those is logged, since its entries are missing from the result
+pub fn entries(
+    guild: GuildId,
+    user: Option<UserId>,
+    action: Option<Action>,
+) -> Result<(Vec<Entry>, usize)> {
+    let (logs, failed) = AuditLog::query(guild).read_with_errors()?;
+
+    if let Some(logger) = logger() {
+        for (req, error) in &failed {
+            logger
+                .warn(format!("Audit log unreadable: {} - {error}", req.id()))
+                .ok();
+        }
+    }
+
+    let mut entries: Vec<_> = logs
+        .into_iter()
+        .flat_map(|(_, log)| log.0)
+        .filter(|e| user.map_or(true, |u| e.involves(u)))
+        .filter(|e| action.map_or(true, |a| e.action == a))
+        .collect();
+
+    entries.sort_by(|a, b| b.time.cmp(&a.time));
+
+    Ok((entries, failed.len()))
+}
+
+fn __filters(user: Option<UserId>, action: Option<Action>) -> [String; 2] {
+    [
+        user.map(|u| u.to_string()).unwrap_or_default(),
+        action.map(|a| (a as u8).to_string()).unwrap_or_default(),
+    ]
+}
+
+fn __page(
+    guild: GuildId,
+    user: Option<UserId>,
+    action: Option<Action>,
+    page: usize,
+) -> Result<(CreateEmbed, Vec<CreateButton>)> {
+    let (entries, unreadable) = entries(guild, user, action)?;
+    let pages = entries.len().div_ceil(PAGE_SIZE).max(1);
+    let page = Pager::wrap(page, pages);
+
+    let lines: Vec<_> = entries
+        .iter()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|e| __clip(e.to_string()))
+        .collect();
+    let description = if lines.is_empty() {
+        "*No matching entries*".to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    let mut footer = format!("Page {page} / {pages}");
+
+    if unreadable > 0 {
+        footer.push_str(&format!(" • {unreadable} log(s) could not be read"));
+    }
+
+    let footer = CreateEmbedFooter::new(footer);
+    let embed = CreateEmbed::new()
+        .color(BOT_COLOR)
+        .description(description)
+        .footer(footer)
+        .title("Audit Log");
+    let buttons = PAGER.buttons(&__filters(user, action), page, pages, false);
+
+    Ok((embed, buttons))
+}
+
+pub fn new() -> CreateCommand {
+    let mut action = CreateCommandOption::new(
+        CommandOptionType::Integer,
+        OPTION_ACTION,
+        "Only show this type of action",
+    );
+
+    for kind in Action::ALL {
+        action = action.add_int_choice(kind.to_string(), kind as i32);
+    }
+
+    CreateCommand::new(NAME)
+        .default_member_permissions(Permissions::MODERATE_MEMBERS)
+        .description(DESCRIPTION)
+        .dm_permission(false)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            OPTION_USER,
+            "Only show actions performed by or on this user",
+        ))
+        .add_option(action)
+}
+
+pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
+    let guild = cmd.guild_id.ok_or(Error::MissingId(Value::Guild))?;
+    let o = &cmd.data.options();
+
+    let user = get_user(o, OPTION_USER).ok().map(|(u, _)| u.id);
+    let action = get_i64(o, OPTION_ACTION)
+        .ok()
+        .map(Action::try_from)
+        .transpose()?;
+
+    let (embed, buttons) = __page(guild, user, action, 1)?;
+    let mut message = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    for button in buttons {
+        message = message.button(button);
+    }
+
+    cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
+        .await
+        .map_err(Error::from)
+}
+
+pub async fn run_component(http: &Http, cpn: &ComponentInteraction) -> Result<()> {
+    let custom_id = CustomId::try_from(cpn.data.custom_id.as_str())?;
+    let guild = cpn.guild_id.ok_or(Error::MissingId(Value::Guild))?;
+    let page = PAGER.step(&custom_id)?;
+
+    let user = match custom_id.args.first().filter(|s| !s.is_empty()) {
+        Some(raw) => match raw.parse() {
+            Ok(id) => Some(UserId::new(id)),
+            Err(_) => return Err(Error::InvalidId(Value::User, raw.clone())),
+        },
+        None => None,
+    };
+    let action = match custom_id.args.get(1).filter(|s| !s.is_empty()) {
+        Some(raw) => match raw.parse::<i64>() {
+            Ok(n) => Some(Action::try_from(n)?),
+            Err(_) => return Err(Error::InvalidValue(Value::Data, raw.clone())),
+        },
+        None => None,
+    };
+
+    let (embed, buttons) = __page(guild, user, action, page)?;
+    let mut message = CreateInteractionResponseMessage::new().embed(embed);
+
+    for button in buttons {
+        message = message.button(button);
+    }
+
+    cpn.create_response(http, CreateInteractionResponse::UpdateMessage(message))
+        .counted("create_interaction_response")
+        .await
+        .map_err(Error::from)
+}
+
+pub fn migrate(report: &mut MigrationReport) -> Result<()> {
+    for req in AuditLog::query(()).reqs()? {
+        report.record(&req);
+    }
+
+    Ok(())
+}
+
+pub fn purge(guild: GuildId) -> Result<()> {
+    storage().clear(&format!("{NAME}/{guild}"))
+}
+
+// entries are exported but never forgotten, since they record what moderators did
+pub fn export(user: UserId, export: &mut data::Export) -> Result<()> {
+    let (logs, failed) = AuditLog::query(()).read_with_errors()?;
+
+    for (req, log) in logs {
+        let entries: Vec<_> = log.0.into_iter().filter(|e| e.involves(user)).collect();
+
+        if !entries.is_empty() {
+            export.push(&req, "audit", &entries)?;
+        }
+    }
+
+    export.skip(&failed);
+
+    Ok(())
+}
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+    async fn run_component(&self, http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+        run_component(http, cpn).await
+    }
+}
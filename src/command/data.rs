@@ -1,27 +1,222 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "data";
+pub const DESCRIPTION: &str = "Displays or manages your stored data";
+
+pub const BUTTON_DELETE: &str = formatcp!("{NAME}_delete");
+
+pub const SUB_ABOUT: &str = "about";
+pub const SUB_EXPORT: &str = "export";
+pub const SUB_DELETE: &str = "delete";
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub record: String,
+    pub kind: &'static str,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Export {
+    pub user: UserId,
+    pub records: Vec<Entry>,
+    pub unreadable: usize,
+}
+
+impl Export {
+    pub const fn new(user: UserId) -> Self {
+        let records = vec![];
+
+        Self {
+            user,
+            records,
+            unreadable: 0,
+        }
+    }
+
+    pub fn push<T, D>(&mut self, req: &Req<T>, kind: &'static str, data: &D) -> Result<()>
+    where
+        T: Send + Sync + Versioned,
+        D: Serialize,
+    {
+        self.records.push(Entry {
+            record: req.id(),
+            kind,
+            data: serde_json::to_value(data)?,
+        });
+
+        Ok(())
+    }
+    // records that could not be read may have belonged to the user, so the export is incomplete
+    pub fn skip<T>(&mut self, failed: &[(Req<T>, Error)])
+    where
+        T: Send + Sync + Versioned,
+    {
+        self.unreadable += failed.len();
+    }
+}
 
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::USE_APPLICATION_COMMANDS)
-        .description("Displays information about data usage and privacy")
+        .description(DESCRIPTION)
         .dm_permission(true)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            SUB_ABOUT,
+            "Displays information about data usage and privacy",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            SUB_EXPORT,
+            "Sends you a copy of all data stored about you",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            SUB_DELETE,
+            "Deletes all data stored about you",
+        ))
 }
 
 pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
-    let user = http.get_current_user().await?;
-    let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
+    let o = &cmd.data.options();
+
+    if get_subcommand(o, SUB_ABOUT).is_ok() {
+        let user = http.get_current_user().counted("get_current_user").await?;
+        let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
+        let embed = CreateEmbed::new()
+            .author(author)
+            .color(BOT_COLOR)
+            .description(include_str!(r"../include/data.txt"))
+            .title("Data Usage and Privacy");
+        let message = CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else if get_subcommand(o, SUB_EXPORT).is_ok() {
+        let user = cmd.user.id;
+        let deferral = Deferral::Reply { ephemeral: true };
+
+        // every record is read to build the export, which can outlast the response window
+        cmd.defer_slow(http, deferral, async {
+            let mut export = Export::new(user);
+
+            apply::export(user, &mut export)?;
+            audit::export(user, &mut export)?;
+            poll::export(user, &mut export)?;
+            role::export(user, &mut export)?;
+
+            let count = export.records.len();
+            let raw = serde_json::to_vec_pretty(&export)?;
+            let file = CreateAttachment::bytes(raw, format!("{NAME}_{user}.json"));
+            let mut embed = CreateEmbed::new()
+                .color(BOT_COLOR)
+                .title(format!("Exported {count} record(s)"));
+
+            if export.unreadable > 0 {
+                embed = embed.description(format!(
+                    "{} stored record(s) could not be read, so this export may be incomplete.",
+                    export.unreadable
+                ));
+            }
+
+            let dm = CreateMessage::new().embed(embed).add_file(file);
+            let channel = user
+                .create_dm_channel(http)
+                .counted("create_private_channel")
+                .await?;
+
+            channel
+                .send_message(http, dm)
+                .counted("create_message")
+                .await?;
+
+            Ok(())
+        })
+        .await?;
+
+        let embed = CreateEmbed::new()
+            .color(BOT_COLOR)
+            .title("Sent your data in a direct message!");
+
+        cmd.reply(http, embed, true).await
+    } else if get_subcommand(o, SUB_DELETE).is_ok() {
+        let custom_id = CustomId::new(BUTTON_DELETE).arg(cmd.user.id);
+        let button = CreateButton::new(custom_id.to_string())
+            .emoji('🗑')
+            .label("Delete")
+            .style(ButtonStyle::Danger);
+        let embed = CreateEmbed::new()
+            .color(BOT_COLOR)
+            .description("This removes your polls, poll replies, applications, and role selectors. Audit log entries are kept. It cannot be undone.")
+            .title("Delete all of your data?");
+        let message = CreateInteractionResponseMessage::new()
+            .button(button)
+            .embed(embed)
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else {
+        Err(Error::InvalidId(Value::Command, cmd.data.name.clone()))
+    }
+}
+pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+    let custom_id = CustomId::try_from(cpn.data.custom_id.as_str())?;
+
+    if custom_id.name != BUTTON_DELETE {
+        return Err(Error::InvalidId(Value::Component, custom_id.name));
+    }
+    let Some(user) = custom_id.args.first() else {
+		return Err(Error::MissingValue(Value::User));
+	};
+    if *user != cpn.user.id.to_string() {
+        return Err(Error::InvalidId(Value::User, user.clone()));
+    }
+
+    let mut count = 0;
+
+    count += apply::forget(cpn.user.id).await?;
+    count += poll::forget(http, cpn.user.id).await?;
+    count += role::forget(cpn.user.id).await?;
+
     let embed = CreateEmbed::new()
-        .author(author)
         .color(BOT_COLOR)
-        .description(include_str!(r"../include/data.txt"))
-        .title("Data Usage and Privacy");
+        .title(format!("Deleted {count} record(s)"));
     let message = CreateInteractionResponseMessage::new()
-        .embed(embed)
-        .ephemeral(true);
+        .components(vec![])
+        .embed(embed);
 
-    cmd.create_response(http, CreateInteractionResponse::Message(message))
+    cpn.create_response(http, CreateInteractionResponse::UpdateMessage(message))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+    async fn run_component(&self, http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+        run_component(http, cpn).await
+    }
+}
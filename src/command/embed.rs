@@ -1,6 +1,7 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "embed";
+pub const DESCRIPTION: &str = "Creates an embedded message";
 
 pub const OPTION_AUTHOR_ICON: &str = "author_icon";
 pub const OPTION_AUTHOR_LINK: &str = "author_link";
@@ -19,7 +20,7 @@ pub const OPTION_EPHEMERAL: &str = "ephemeral";
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::EMBED_LINKS)
-        .description("Creates an embedded message")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(CreateCommandOption::new(
             CommandOptionType::String,
@@ -146,7 +147,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
 
     if let Ok(hex) = get_str(o, OPTION_EMBED_COLOR) {
         let color = if hex.is_empty() {
-            let user = http.get_user(cmd.user.id).await?;
+            let user = http.get_user(cmd.user.id).counted("get_user").await?;
 
             user.accent_colour
         } else {
@@ -209,6 +210,26 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         .ephemeral(ephemeral);
 
     cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+}
@@ -1,36 +1,50 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "help";
+pub const DESCRIPTION: &str = "Displays a list of bot commands";
 
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::USE_APPLICATION_COMMANDS)
-        .description("Displays a list of bot commands")
+        .description(DESCRIPTION)
         .dm_permission(false)
 }
 
 pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
-    let user = http.get_current_user().await?;
-    let mut commands = http.get_global_application_commands().await?;
+    let user = http.get_current_user().counted("get_current_user").await?;
+    let mut commands = http
+        .get_global_application_commands()
+        .counted("get_global_application_commands")
+        .await?;
     let mut description = include_str!(r"../include/help/start.txt").to_string();
 
-    if commands.is_empty() {
-        description.push_str("\n*No global commands found...*\n");
-    } else {
-        commands.sort_by_key(|c| c.name.clone());
+    if let Some(guild) = cmd.guild_id {
+        commands.extend(
+            http.get_guild_application_commands(guild)
+                .counted("get_guild_application_commands")
+                .await?,
+        );
+    }
 
-        for command in commands {
-            let label = format!(
-                "\n</{}:{}> - {}",
-                command.name, command.id, command.description
-            );
+    let mut registered: Vec<_> = REGISTRY.iter().collect();
+
+    registered.sort_by_key(|c| c.name());
 
-            description.push_str(&label);
-        }
+    for command in registered {
+        // registered commands are mentioned by id, which can only be known once discord has them
+        let mention = commands
+            .iter()
+            .find(|c| c.name == command.name())
+            .map_or_else(
+                || format!("`/{}`", command.name()),
+                |c| format!("</{}:{}>", c.name, c.id),
+            );
+        let text = command.description();
 
-        description.push('\n');
+        description.push_str(&format!("\n{mention} - {text}"));
     }
 
+    description.push('\n');
     description.push_str(include_str!(r"../include/help/end.txt"));
 
     let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
@@ -45,6 +59,26 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         .ephemeral(true);
 
     cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+}
@@ -1,6 +1,9 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "offer";
+pub const DESCRIPTION: &str = "Create a new trade offer";
+
+pub const COOLDOWN: Cooldown = Cooldown::new(NAME, Bucket::User, 30);
 
 pub const OPTION_OFFER: &str = "offer";
 pub const OPTION_PRICE: &str = "price";
@@ -9,7 +12,7 @@ pub const OPTION_MINUTES: &str = "minutes";
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::SEND_MESSAGES)
-        .description("Create a new trade offer")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(
             CreateCommandOption::new(
@@ -44,7 +47,7 @@ pub fn new() -> CreateCommand {
 }
 
 pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
-    let user = http.get_user(cmd.user.id).await?;
+    let user = http.get_user(cmd.user.id).counted("get_user").await?;
 
     let o = &cmd.data.options();
     let offer = get_str(o, OPTION_OFFER)?;
@@ -63,6 +66,29 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
 
     let message = CreateInteractionResponseMessage::new().embed(embed);
     cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(COOLDOWN)
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+}
@@ -3,6 +3,9 @@ use rand::{thread_rng, Rng};
 use crate::prelude::*;
 
 pub const NAME: &str = "oracle";
+pub const DESCRIPTION: &str = "Asks the Oracle a question";
+
+pub const COOLDOWN: Cooldown = Cooldown::new(NAME, Bucket::Channel, 10);
 
 pub const OPTION_QUESTION: &str = "question";
 
@@ -73,7 +76,7 @@ impl AsEmbed<(&str, &str)> for Reply {
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::SEND_MESSAGES)
-        .description("Asks the Oracle a question")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(
             CreateCommandOption::new(
@@ -88,7 +91,7 @@ pub fn new() -> CreateCommand {
 }
 
 pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
-    let name = cmd.user.id.to_user(http).await?.tag();
+    let name = cmd.user.id.to_user(http).counted("get_user").await?.tag();
     let o = &cmd.data.options();
     let query = get_str(o, OPTION_QUESTION)?;
 
@@ -98,6 +101,29 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
 
     let message = CreateInteractionResponseMessage::new().embed(embed);
     cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(COOLDOWN)
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+}
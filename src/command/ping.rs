@@ -1,11 +1,12 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "ping";
+pub const DESCRIPTION: &str = "Check the bot's API response time";
 
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::USE_APPLICATION_COMMANDS)
-        .description("Check the bot's API response time")
+        .description(DESCRIPTION)
         .dm_permission(true)
 }
 
@@ -16,16 +17,40 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         .ephemeral(true);
 
     cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
         .await?;
 
-    let res = cmd.get_response(http).await?;
+    let res = cmd
+        .get_response(http)
+        .counted("get_interaction_response")
+        .await?;
     let sent = res.id.created_at().timestamp_millis();
     let received = cmd.id.created_at().timestamp_millis();
     let ms = sent - received;
 
     embed = embed.title(format!("Pong! ({ms}ms)"));
     cmd.edit_response(http, EditInteractionResponse::new().embed(embed))
+        .counted("edit_interaction_response")
         .await?;
 
     Ok(())
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::prelude::*;
 
 use self::form::*;
@@ -9,6 +11,11 @@ pub mod input;
 pub mod output;
 
 pub const NAME: &str = "poll";
+pub const DESCRIPTION: &str = "Create or manage polls";
+
+pub const BUTTON_COOLDOWN: Cooldown = Cooldown::new(formatcp!("{NAME}_buttons"), Bucket::User, 2);
+// only the buttons that reply to a poll are rate limited, so paging and removal stay responsive
+pub const COOLDOWN_BUTTONS: [&str; 3] = [BUTTON_CHOICE, BUTTON_RESPONSE, BUTTON_RAFFLE];
 
 pub const GROUP_INPUT: &str = "input";
 
@@ -35,7 +42,7 @@ pub const OPTION_PLACEHOLDER: &str = "placeholder";
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::SEND_MESSAGES)
-        .description("Create or manage polls")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(
             CreateCommandOption::new(
@@ -223,11 +230,21 @@ pub fn new() -> CreateCommand {
                     .clone(),
                 ),
             )
-            .add_sub_option(CreateCommandOption::new(
-                CommandOptionType::SubCommand,
-                SUB_DISCARD,
-                "Discards poll inputs; does not work with Raffle polls",
-            )),
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    SUB_DISCARD,
+                    "Discards poll inputs; does not work with Raffle polls",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        OPTION_LABEL,
+                        "The label of the poll input to discard; omit to choose from a list",
+                    )
+                    .set_autocomplete(true),
+                ),
+            ),
         )
 }
 
@@ -235,6 +252,7 @@ pub fn new() -> CreateCommand {
 pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
     let guild = cmd.guild_id.ok_or(Error::MissingId(Value::Guild))?;
     let o = &cmd.data.options();
+    let _lock = Form::new_req((guild, cmd.user.id)).lock().await?;
 
     if let Ok(o) = get_subcommand(o, SUB_CREATE) {
         if Form::read((guild, cmd.user.id)).is_ok() {
@@ -268,6 +286,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(builder))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if let Ok(o) = get_subcommand(o, SUB_DISCARD) {
@@ -281,7 +300,12 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         }
 
         if let Ok(anchor) = form.anchor() {
-            anchor.to_message(http).await?.delete(http).await?;
+            anchor
+                .to_message(http)
+                .await?
+                .delete(http)
+                .counted("delete_message")
+                .await?;
         }
 
         form.remove(guild)?;
@@ -294,6 +318,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(builder))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if let Ok(o) = get_subcommand(o, SUB_MODIFY) {
@@ -338,6 +363,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(builder))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if get_subcommand(o, SUB_PREVIEW).is_ok() {
@@ -358,6 +384,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         }
 
         cmd.create_response(http, CreateInteractionResponse::Message(builder))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if get_subcommand(o, SUB_SEND).is_ok() {
@@ -375,18 +402,16 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         }
 
         // disable force sending for now
-        form.send(http, guild, cmd.channel_id, true).await?;
+        let deferral = Deferral::Reply { ephemeral: true };
+
+        cmd.defer_slow(http, deferral, form.send(http, guild, cmd.channel_id, true))
+            .await?;
 
         let builder = CreateEmbed::new()
             .color(BOT_COLOR)
             .title("Your poll has been published!");
-        let builder = CreateInteractionResponseMessage::new()
-            .embed(builder)
-            .ephemeral(true);
 
-        cmd.create_response(http, CreateInteractionResponse::Message(builder))
-            .await
-            .map_err(Error::from)
+        cmd.reply(http, builder, true).await
     } else if get_subcommand(o, SUB_CLOSE).is_ok() {
         let Ok(form) = Form::read((guild, cmd.user.id)) else {
             return Err(Error::Other("You do not have a poll"));
@@ -395,8 +420,18 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             return Err(Error::Other("Your poll has not been sent"));
         }
 
+        let anchor = form.anchor()?;
+
         form.close(http).await?;
 
+        let entry = audit::Entry::new(
+            cmd.user.id,
+            audit::Action::PollClosed,
+            audit::Target::Message(anchor),
+        );
+
+        audit::record(guild, entry).await;
+
         let builder = CreateEmbed::new()
             .color(BOT_COLOR)
             .title("Your poll has been closed!");
@@ -405,6 +440,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(builder))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if let Ok(o) = get_subcommand_group(o, GROUP_INPUT) {
@@ -453,10 +489,11 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
                 .ephemeral(true);
 
             cmd.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
-        } else if get_subcommand(o, SUB_DISCARD).is_ok() {
-            let Ok(form) = Form::read((guild, cmd.user.id)) else {
+        } else if let Ok(o) = get_subcommand(o, SUB_DISCARD) {
+            let Ok(mut form) = Form::read((guild, cmd.user.id)) else {
                 return Err(Error::Other("You do not have a poll"));
             };
             if form.is_anchored() {
@@ -466,9 +503,27 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
                 return Err(Error::Other("Your poll does not have any inputs"));
             }
 
-            let builder = form.as_remove_message(false);
+            let builder = if let Ok(label) = get_str(o, OPTION_LABEL) {
+                let Some(index) = form.inputs.iter().position(|i| i.label() == label) else {
+                    return Err(Error::Other("The given input does not exist"));
+                };
+
+                form.inputs.remove(index);
+                form.write(guild)?;
+
+                let builder = CreateEmbed::new()
+                    .color(BOT_COLOR)
+                    .title(format!("Removed input '{label}'!"));
+
+                CreateInteractionResponseMessage::new()
+                    .embed(builder)
+                    .ephemeral(true)
+            } else {
+                form.as_remove_message(false)
+            };
 
             cmd.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         } else {
@@ -478,6 +533,37 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         Err(Error::InvalidId(Value::Command, cmd.data.name.clone()))
     }
 }
+fn __archived_response() -> CreateInteractionResponse {
+    let embed = CreateEmbed::new()
+        .color(BOT_COLOR)
+        .description("This poll is old enough that its results are no longer kept.")
+        .title("Results archived");
+    let message = CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .ephemeral(true);
+
+    CreateInteractionResponse::Message(message)
+}
+pub async fn run_autocomplete(http: &Http, cmd: &CommandInteraction) -> Result<()> {
+    let guild = cmd.guild_id.ok_or(Error::MissingId(Value::Guild))?;
+    let focused = get_focused(cmd)?;
+
+    if focused != OPTION_LABEL {
+        return Err(Error::InvalidValue(Value::Data, focused.to_string()));
+    }
+
+    let choices = Form::read((guild, cmd.user.id)).map_or_else(
+        |_| vec![],
+        |f| {
+            f.inputs
+                .iter()
+                .map(|i| (i.label().to_string(), i.label().to_string()))
+                .collect()
+        },
+    );
+
+    suggest(http, cmd, choices).await
+}
 #[allow(clippy::map_entry, clippy::too_many_lines)]
 pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
     let custom_id = CustomId::try_from(cpn.data.custom_id.as_str())?;
@@ -500,6 +586,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
                 return Err(Error::InvalidId(Value::Data, index.clone()));
             };
 
+            let _lock = Form::new_req((guild, user)).lock().await?;
             let mut form = Form::read((guild, user))?;
 
             if user != cpn.user.id {
@@ -525,6 +612,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             };
 
             cpn.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -544,6 +632,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
                 return Err(Error::InvalidId(Value::Data, index.clone()));
             };
 
+            let _lock = Form::new_req((guild, user)).lock().await?;
             let mut form = Form::read((guild, user))?;
 
             if user == cpn.user.id {
@@ -566,6 +655,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
 
                     return cpn
                         .create_response(http, CreateInteractionResponse::Message(builder))
+                        .counted("create_interaction_response")
                         .await
                         .map_err(Error::from);
                 }
@@ -579,6 +669,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
                 .ephemeral(true);
 
             cpn.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -600,6 +691,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             let builder = form.try_as_modal(())?;
 
             cpn.create_response(http, CreateInteractionResponse::Modal(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -612,6 +704,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             };
             let user = UserId::new(user);
 
+            let _lock = Form::new_req((guild, user)).lock().await?;
             let mut form = Form::read((guild, user))?;
 
             if user == cpn.user.id {
@@ -636,6 +729,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
                 .ephemeral(true);
 
             cpn.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -656,7 +750,17 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             };
             let message = MessageId::new(message);
 
-            let mut form = Form::read((guild, user, message))?;
+            let mut form = match Form::read((guild, user, message)) {
+                Ok(form) => form,
+                Err(error) if error.is_not_found() => {
+                    return cpn
+                        .create_response(http, __archived_response())
+                        .counted("create_interaction_response")
+                        .await
+                        .map_err(Error::from);
+                }
+                Err(error) => return Err(error),
+            };
 
             if form.content.hide_results && cpn.user.id != user {
                 return Err(Error::Other("The results of this poll are private"));
@@ -673,6 +777,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             }
 
             cpn.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -693,19 +798,19 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             };
             let message = MessageId::new(message);
 
-            let Some(page) = custom_id.args.get(2) else {
-                return Err(Error::MissingId(Value::Data));
-            };
-            let Ok(page) = page.parse::<usize>() else {
-                return Err(Error::InvalidId(Value::Data, page.clone()));
-            };
-            let page = match custom_id.name.as_str() {
-                BUTTON_LAST => page.saturating_sub(1),
-                BUTTON_NEXT => page.saturating_add(1),
-                _ => return Err(Error::InvalidId(Value::Component, custom_id.name)),
-            };
+            let page = PAGER.step(&custom_id)?;
 
-            let mut form = Form::read((guild, user, message))?;
+            let mut form = match Form::read((guild, user, message)) {
+                Ok(form) => form,
+                Err(error) if error.is_not_found() => {
+                    return cpn
+                        .create_response(http, __archived_response())
+                        .counted("create_interaction_response")
+                        .await
+                        .map_err(Error::from);
+                }
+                Err(error) => return Err(error),
+            };
 
             if form.content.hide_results && cpn.user.id != user {
                 return Err(Error::Other("The results of this poll are private"));
@@ -722,6 +827,7 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
             }
 
             cpn.create_response(http, CreateInteractionResponse::Message(builder))
+                .counted("create_interaction_response")
                 .await
                 .map_err(Error::from)
         }
@@ -745,6 +851,7 @@ pub async fn run_modal(http: &Http, mdl: &ModalInteraction) -> Result<()> {
     };
     let user = UserId::new(user);
 
+    let _lock = Form::new_req((guild, user)).lock().await?;
     let mut form = Form::read((guild, user))?;
 
     if user == mdl.user.id {
@@ -770,20 +877,38 @@ pub async fn run_modal(http: &Http, mdl: &ModalInteraction) -> Result<()> {
         .ephemeral(true);
 
     mdl.create_response(http, CreateInteractionResponse::Message(builder))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
 
-pub async fn check(http: &Http) -> Result<()> {
-    let Ok(mut active) = Active::read(()) else {
-        return Ok(());
+// closes every expired poll, returning the polls that failed so they can be reported per guild
+pub async fn check(http: &Http) -> Result<Vec<(GuildId, Error)>> {
+    let Ok(active) = Active::read(()) else {
+        return Ok(vec![]);
     };
+    let departed = Departed::read(()).unwrap_or_default();
     let mut invalid = vec![];
+    let mut failed = vec![];
 
     for key in &active.0 {
-        let Ok(form) = Form::read(*key) else {
-            invalid.push(*key);
+        // the bot can no longer reach these polls, so leave them until the guild returns or is
+        // purged
+        if departed.is_pending(key.0) {
             continue;
+        }
+
+        let _lock = Form::new_req(*key).lock().await?;
+        let form = match Form::read(*key) {
+            Ok(form) => form,
+            Err(error) if error.is_not_found() => {
+                invalid.push(*key);
+                continue;
+            }
+            Err(error) => {
+                failed.push((key.0, error));
+                continue;
+            }
         };
         let Ok(anchor) = form.anchor() else {
             invalid.push(*key);
@@ -794,11 +919,16 @@ pub async fn check(http: &Http) -> Result<()> {
         let duration = form.content.hours * 60 * 60 * 1000;
 
         if Utc::now().timestamp_millis() >= sent + duration {
-            form.close(http).await?;
+            if let Err(error) = form.close(http).await {
+                failed.push((key.0, error));
+            }
         }
     }
 
     if !invalid.is_empty() {
+        let _lock = Active::new_req(()).lock().await?;
+        let mut active = Active::read(()).unwrap_or_default();
+
         for key in invalid {
             active.0.remove(&key);
         }
@@ -806,5 +936,165 @@ pub async fn check(http: &Http) -> Result<()> {
         active.write(())?;
     }
 
+    Ok(failed)
+}
+
+// applies each guild's retention settings to closed polls, returning the archived and removed
+// counts; this reads every poll, so it runs on a blocking thread
+pub fn archive() -> Result<(usize, usize)> {
+    const DAY: i64 = 24 * 60 * 60 * 1000;
+
+    let now = Utc::now().timestamp_millis();
+    let mut retention = BTreeMap::new();
+    let mut archived = 0;
+    let mut removed = 0;
+
+    for req in Form::query(()).reqs()? {
+        let _lock = req.blocking_lock()?;
+        let Ok(mut form) = req.read() else {
+            continue;
+        };
+        let Ok(anchor) = form.anchor() else {
+            continue;
+        };
+        if !form.is_closed() {
+            continue;
+        }
+
+        let policy = *retention.entry(anchor.guild).or_insert_with(|| {
+            settings::Settings::read(anchor.guild)
+                .unwrap_or_default()
+                .retention
+        });
+        let days = (now - form.closes_at_ms()) / DAY;
+        let expired = |limit: Option<u32>| limit.map_or(false, |n| days >= i64::from(n));
+
+        if expired(policy.records) {
+            req.remove()?;
+            removed += 1;
+        } else if expired(policy.replies) && form.archive() {
+            req.write(&form)?;
+            archived += 1;
+        }
+    }
+
+    Ok((archived, removed))
+}
+
+pub fn migrate(report: &mut MigrationReport) -> Result<()> {
+    report.record(&Active::new_req(()));
+
+    for req in Form::query(()).reqs()? {
+        report.record(&req);
+    }
+
     Ok(())
 }
+
+pub async fn purge(guild: GuildId) -> Result<()> {
+    let _lock = Active::new_req(()).lock().await?;
+
+    if let Ok(mut active) = Active::read(()) {
+        active.0.retain(|(g, _)| *g != guild);
+        active.write(())?;
+    }
+
+    storage().clear(&format!("{NAME}/{guild}"))
+}
+
+pub fn export(user: UserId, export: &mut data::Export) -> Result<()> {
+    let (forms, failed) = Form::query(()).read_with_errors()?;
+
+    for (req, form) in forms {
+        if form.user == user {
+            export.push(&req, "poll", &form.redacted())?;
+        } else if let Some(reply) = form.replies.get(&user) {
+            export.push(&req, "poll_reply", reply)?;
+        }
+    }
+
+    export.skip(&failed);
+
+    Ok(())
+}
+
+pub async fn forget(http: &Http, user: UserId) -> Result<usize> {
+    let mut count = 0;
+
+    for req in Form::query(()).reqs()? {
+        let _lock = req.lock().await?;
+        let Ok(mut form) = req.read() else {
+            continue;
+        };
+
+        if form.user == user {
+            if let Ok(anchor) = form.anchor() {
+                let open = {
+                    let _lock = Active::new_req(()).lock().await?;
+                    let mut active = Active::read(()).unwrap_or_default();
+                    let open = active.0.remove(&(anchor.guild, user));
+
+                    if open {
+                        active.write(())?;
+                    }
+
+                    open
+                };
+
+                // an open poll's buttons would only fail once its record is gone; the message may
+                // already be deleted, which should not stop the rest of the removal
+                if open {
+                    if let (Err(error), Some(logger)) = (form.disable(http).await, logger()) {
+                        logger
+                            .warn(format!("Disabling failed: {anchor} - {error}"))
+                            .ok();
+                    }
+                }
+            }
+
+            req.remove()?;
+        } else if form.forget(user) {
+            req.write(&form)?;
+        } else {
+            continue;
+        }
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+    fn component_cooldown(&self) -> Option<Cooldown> {
+        Some(BUTTON_COOLDOWN)
+    }
+    fn uses_cooldown(&self, custom_id: &CustomId) -> bool {
+        COOLDOWN_BUTTONS.contains(&custom_id.name.as_str())
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+    async fn run_autocomplete(&self, ctx: &Context, cmd: &CommandInteraction) -> Result<()> {
+        run_autocomplete(&ctx.http, cmd).await
+    }
+    async fn run_component(&self, http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+        run_component(http, cpn).await
+    }
+    async fn run_modal(&self, http: &Http, mdl: &ModalInteraction) -> Result<()> {
+        run_modal(http, mdl).await
+    }
+}
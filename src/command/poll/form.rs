@@ -44,6 +44,10 @@ impl Display for Kind {
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Active(pub BTreeSet<(GuildId, UserId)>);
 
+impl Versioned for Active {
+    const MIGRATIONS: &'static [Migration] = &[legacy::<Self>];
+}
+
 impl NewReq<()> for Active {
     fn new_req(_: ()) -> Req<Self> {
         Req::new(NAME, ".dat")
@@ -102,14 +106,46 @@ impl Form {
 
         self.output.get_or_insert_with(|| Output::new(&cloned))
     }
-    pub fn closes_at(&self) -> TimeString {
+    pub fn redacted(&self) -> Self {
+        let mut form = self.clone();
+
+        form.replies.clear();
+        form.output = None;
+        form
+    }
+    pub fn forget(&mut self, user: UserId) -> bool {
+        let removed = self.replies.remove(&user).is_some();
+        let forgotten = self.output.as_mut().map_or(false, |o| o.forget(user));
+
+        removed || forgotten
+    }
+    pub fn closes_at_ms(&self) -> i64 {
         let ms = self.content.hours * 60 * 60 * 1000;
         let base = self.anchor().map_or_else(
             |_| Utc::now().timestamp_millis(),
             |a| a.message.created_at().timestamp_millis(),
         );
 
-        TimeString::new(base + ms)
+        base + ms
+    }
+    pub fn closes_at(&self) -> TimeString {
+        TimeString::new(self.closes_at_ms())
+    }
+    pub const fn is_closed(&self) -> bool {
+        self.output.is_some()
+    }
+    // drops individual replies and votes from a closed poll, keeping only its totals
+    pub fn archive(&mut self) -> bool {
+        let Some(output) = self.output.as_ref() else {
+            return false;
+        };
+        if let Output::Archived(_) = output {
+            return false;
+        }
+
+        self.output = Some(Output::Archived(ArchivedOutputData::new(self, output)));
+        self.replies.clear();
+        true
     }
 
     pub fn as_remove_buttons(&self, disabled: bool) -> Vec<CreateButton> {
@@ -156,7 +192,7 @@ impl Form {
         ]
     }
     pub async fn as_results_embed(&self, http: &Http) -> Result<CreateEmbed> {
-        let user = http.get_user(self.user).await?;
+        let user = http.get_user(self.user).counted("get_user").await?;
         let color = user.accent_colour.unwrap_or(BOT_COLOR);
         let mut builder = CreateEmbed::new().color(color).title("Poll Results");
 
@@ -194,43 +230,64 @@ impl Form {
         }
 
         if let Ok(anchor) = self.anchor() {
-            anchor.to_message(http).await?.delete(http).await?;
+            anchor
+                .to_message(http)
+                .await?
+                .delete(http)
+                .counted("delete_message")
+                .await?;
         }
 
-        let guild = http.get_guild(guild).await?;
-        let Some(channel) = guild.channels(http).await?.remove(&channel) else {
+        let guild = http.get_guild(guild).counted("get_guild").await?;
+        let mut channels = guild.channels(http).counted("get_channels").await?;
+        let Some(channel) = channels.remove(&channel) else {
             return Err(Error::InvalidId(Value::Channel, channel.to_string()));
         };
 
         let builder = self.as_message(http, false).await?;
-        let message = channel.send_message(http, builder).await?;
+        let message = channel
+            .send_message(http, builder)
+            .counted("create_message")
+            .await?;
         self.anchor = Some(Anchor::try_from((guild.id, &message))?);
         self.write(self.anchor()?.guild)?;
 
+        let _lock = Active::new_req(()).lock().await?;
         let mut active = Active::read(()).unwrap_or_default();
         active.0.insert((self.anchor()?.guild, self.user));
         active.write(())
     }
+    // leaves the posted message in place, but with every button disabled
+    pub async fn disable(&self, http: &Http) -> Result<()> {
+        let mut message = self.anchor()?.to_message(http).await?;
+        let mut builder = EditMessage::new().components(vec![]);
+
+        for button in self.as_buttons(true, ()) {
+            builder = builder.button(button);
+        }
+
+        message.edit(http, builder).counted("edit_message").await?;
+
+        Ok(())
+    }
     pub async fn close(mut self, http: &Http) -> Result<()> {
         let Ok(anchor) = self.anchor() else {
             return Err(Error::Other("The poll has not been sent"));
         };
 
+        let _lock = Active::new_req(()).lock().await?;
         let mut active = Active::read(()).unwrap_or_default();
         active.0.remove(&(anchor.guild, self.user));
         active.write(())?;
 
-        let mut message = anchor.to_message(http).await?;
-        let mut builder = EditMessage::new().components(vec![]);
-
-        for button in self.as_buttons(true, ()) {
-            builder = builder.button(button);
-        }
-
-        message.edit(http, builder).await?;
+        self.disable(http).await?;
 
         let builder = self.as_results_message(http, false).await?;
-        anchor.channel.send_message(http, builder).await?;
+        anchor
+            .channel
+            .send_message(http, builder)
+            .counted("create_message")
+            .await?;
 
         self.output = Some(Output::new(&self));
         self.write((anchor.guild, anchor.message))?;
@@ -277,6 +334,10 @@ impl Anchored for Form {
     }
 }
 
+impl Versioned for Form {
+    const MIGRATIONS: &'static [Migration] = &[legacy::<Self>];
+}
+
 impl NewReq<(GuildId, UserId)> for Form {
     fn new_req((guild, user): (GuildId, UserId)) -> Req<Self> {
         Req::new(format!("{NAME}/{guild}"), user)
@@ -289,6 +350,22 @@ impl NewReq<(GuildId, UserId, MessageId)> for Form {
     }
 }
 
+impl NewReqDir<()> for Form {
+    fn new_req_dir(_: ()) -> ReqDir<Self> {
+        ReqDir::new(NAME)
+    }
+
+    fn query(_: ()) -> Query<Self> {
+        Self::new_req_dir(()).query().deep().exclude(".dat")
+    }
+}
+
+impl NewReqDir<(GuildId, UserId)> for Form {
+    fn new_req_dir((guild, user): (GuildId, UserId)) -> ReqDir<Self> {
+        ReqDir::new(format!("{NAME}/{guild}/{user}"))
+    }
+}
+
 impl AsReq<GuildId> for Form {
     fn as_req(&self, guild: GuildId) -> Req<Self> {
         Self::new_req((guild, self.user))
@@ -314,7 +391,7 @@ impl AsButtonVec<()> for Form {
 #[async_trait]
 impl AsEmbedAsync<()> for Form {
     async fn as_embed(&self, http: &Http, _: ()) -> Result<CreateEmbed> {
-        let user = http.get_user(self.user).await?;
+        let user = http.get_user(self.user).counted("get_user").await?;
 
         let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
 
@@ -6,6 +6,7 @@ use super::*;
 
 pub const BUTTON_LAST: &str = formatcp!("{NAME}_last");
 pub const BUTTON_NEXT: &str = formatcp!("{NAME}_next");
+pub const PAGER: Pager = Pager::new(BUTTON_LAST, BUTTON_NEXT);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChoiceOutputData {
@@ -50,6 +51,9 @@ impl ChoiceOutputData {
     pub fn pages(&self) -> usize {
         self.votes.len() + 1
     }
+    pub fn total(&self) -> usize {
+        self.votes.iter().map(|(_, users)| users.len()).sum()
+    }
 
     #[allow(clippy::cast_precision_loss)]
     fn __percent(votes: usize, total: usize) -> f64 {
@@ -73,8 +77,8 @@ impl ChoiceOutputData {
     }
 
     async fn __overview(&self, http: &Http, form: Form) -> Result<CreateEmbed> {
-        let user = http.get_user(form.user).await?;
-        let total = form.replies.len();
+        let user = http.get_user(form.user).counted("get_user").await?;
+        let total = self.total();
 
         let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
         let footer = CreateEmbedFooter::new(format!("Page 1 / {}", self.pages()));
@@ -116,8 +120,8 @@ impl ChoiceOutputData {
 			return Err(Error::MissingValue(Value::Other("Input")));
 		};
 
-        let user = http.get_user(form.user).await?;
-        let total = form.replies.len();
+        let user = http.get_user(form.user).counted("get_user").await?;
+        let total = self.total();
 
         let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
         let footer = CreateEmbedFooter::new(format!("Page {page} / {}", self.pages()));
@@ -189,7 +193,7 @@ impl ResponseOutputData {
     }
 
     async fn __overview(&self, http: &Http, form: Form) -> Result<CreateEmbed> {
-        let user = http.get_user(form.user).await?;
+        let user = http.get_user(form.user).counted("get_user").await?;
 
         let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
         let footer = CreateEmbedFooter::new(format!("Page 1 / {}", self.pages()));
@@ -216,7 +220,7 @@ impl ResponseOutputData {
 			return Err(Error::MissingValue(Value::Other("Entry")))
 		};
 
-        let user = http.get_user(*user).await?;
+        let user = http.get_user(*user).counted("get_user").await?;
 
         let author = if form.content.hide_members {
             CreateEmbedAuthor::new("Anonymous User").icon_url(user.default_avatar_url())
@@ -302,7 +306,7 @@ impl RaffleOutputData {
 #[async_trait]
 impl AsEmbedAsync<Form> for RaffleOutputData {
     async fn as_embed(&self, http: &Http, form: Form) -> Result<CreateEmbed> {
-        let user = http.get_user(form.user).await?;
+        let user = http.get_user(form.user).counted("get_user").await?;
 
         let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
         let footer = CreateEmbedFooter::new("Page 1 / 1");
@@ -340,11 +344,102 @@ impl AsEmbedAsync<Form> for RaffleOutputData {
     }
 }
 
+// the totals that remain once a poll's replies have been archived, with no per-user data
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedOutputData {
+    pub kind: Kind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub votes: Vec<(usize, usize)>,
+    pub entries: usize,
+    pub winner: Option<UserId>,
+}
+
+impl ArchivedOutputData {
+    pub fn new(form: &Form, output: &Output) -> Self {
+        let mut data = Self {
+            kind: form.kind,
+            votes: vec![],
+            entries: form.replies.len(),
+            winner: None,
+        };
+
+        match output {
+            Output::Choice(choice) => {
+                data.votes = choice.votes.iter().map(|(i, u)| (*i, u.len())).collect();
+                data.entries = choice.total();
+            }
+            Output::Response(response) => data.entries = response.answers.len(),
+            Output::Raffle(raffle) => data.winner = raffle.winner,
+            Output::Archived(archived) => data = archived.clone(),
+        }
+
+        data
+    }
+
+    #[allow(clippy::unused_self)]
+    pub const fn pages(&self) -> usize {
+        1
+    }
+}
+
+#[async_trait]
+impl AsEmbedAsync<Form> for ArchivedOutputData {
+    async fn as_embed(&self, http: &Http, form: Form) -> Result<CreateEmbed> {
+        let user = http.get_user(form.user).counted("get_user").await?;
+
+        let author = CreateEmbedAuthor::new(user.tag()).icon_url(user.face());
+        let footer = CreateEmbedFooter::new("Page 1 / 1");
+        let mut description = match self.kind {
+            Kind::Choice => format!("**Total Votes:** {}\n\n", self.entries),
+            Kind::Response => format!("**Total Responses:** {}\n\n", self.entries),
+            Kind::Raffle => format!("**Total Entries:** {}\n", self.entries),
+        };
+
+        for (index, votes) in &self.votes {
+            let Some(Input::Choice(data)) = form.inputs.get(*index) else {
+                continue;
+            };
+
+            let percent = ChoiceOutputData::__percent(*votes, self.entries);
+            let graph = ChoiceOutputData::__graph(percent, 10);
+            let votes = format!("{votes} votes ({:.1}%)", percent * 100.0);
+
+            description.push_str(&format!("{graph} **{}** - {votes}\n", data.label));
+        }
+
+        if self.kind == Kind::Raffle {
+            let winner = self.winner.map_or_else(
+                || "**Winner:** *No responses*".to_string(),
+                |winner| format!("**Winner:** <@{winner}>"),
+            );
+
+            description.push_str(&format!("{winner}\n\n"));
+        }
+
+        description.push_str("*Individual replies have been archived*");
+
+        let embed = CreateEmbed::new()
+            .author(author)
+            .color(user.accent_colour.unwrap_or(BOT_COLOR))
+            .description(description)
+            .footer(footer)
+            .thumbnail(user.face())
+            .title("Poll Results: Overview");
+
+        if let Ok(anchor) = form.anchor() {
+            Ok(embed.url(anchor.to_string()))
+        } else {
+            Ok(embed)
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Output {
     Choice(ChoiceOutputData),
     Response(ResponseOutputData),
     Raffle(RaffleOutputData),
+    Archived(ArchivedOutputData),
 }
 
 impl Output {
@@ -356,23 +451,36 @@ impl Output {
         }
     }
 
+    pub fn forget(&mut self, user: UserId) -> bool {
+        match self {
+            Self::Choice(data) => data
+                .votes
+                .iter_mut()
+                .fold(false, |found, (_, users)| users.remove(&user) || found),
+            Self::Response(data) => data.answers.remove(&user).is_some(),
+            Self::Raffle(RaffleOutputData { winner })
+            | Self::Archived(ArchivedOutputData { winner, .. }) => {
+                let found = *winner == Some(user);
+
+                if found {
+                    *winner = None;
+                }
+
+                found
+            }
+        }
+    }
+
     pub fn pages(&self) -> usize {
         match self {
             Self::Choice(data) => data.pages(),
             Self::Response(data) => data.pages(),
             Self::Raffle(data) => data.pages(),
+            Self::Archived(data) => data.pages(),
         }
     }
     pub fn wrap_page(&self, page: usize) -> usize {
-        let pages = self.pages();
-
-        if page == 0 {
-            pages
-        } else if page > pages {
-            1
-        } else {
-            page
-        }
+        Pager::wrap(page, self.pages())
     }
 }
 
@@ -382,19 +490,9 @@ impl AsButtonVec<(UserId, MessageId, usize)> for Output {
         disabled: bool,
         (user, message, page): (UserId, MessageId, usize),
     ) -> Vec<CreateButton> {
-        let page = self.wrap_page(page);
-        let disabled = disabled || self.pages() == 1;
-
-        let last = CreateButton::new(CustomId::new(BUTTON_LAST).arg(user).arg(message).arg(page))
-            .disabled(disabled)
-            .emoji('⬅')
-            .style(ButtonStyle::Secondary);
-        let next = CreateButton::new(CustomId::new(BUTTON_NEXT).arg(user).arg(message).arg(page))
-            .disabled(disabled)
-            .emoji('➡')
-            .style(ButtonStyle::Secondary);
-
-        vec![last, next]
+        let args = [user.to_string(), message.to_string()];
+
+        PAGER.buttons(&args, page, self.pages(), disabled)
     }
 }
 
@@ -407,6 +505,7 @@ impl AsEmbedAsync<(Form, usize)> for Output {
             Self::Choice(data) => data.as_embed(http, (form, page)).await,
             Self::Response(data) => data.as_embed(http, (form, page)).await,
             Self::Raffle(data) => data.as_embed(http, form).await,
+            Self::Archived(data) => data.as_embed(http, form).await,
         }
     }
 }
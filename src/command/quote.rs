@@ -1,6 +1,9 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "quote";
+pub const DESCRIPTION: &str = "Quote something that a user said!";
+
+pub const COOLDOWN: Cooldown = Cooldown::new(NAME, Bucket::Channel, 10);
 
 pub const OPTION_USER: &str = "user";
 pub const OPTION_TEXT: &str = "text";
@@ -8,7 +11,7 @@ pub const OPTION_TEXT: &str = "text";
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::SEND_MESSAGES)
-        .description("Quote something that a user said!")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(
             CreateCommandOption::new(CommandOptionType::User, OPTION_USER, "Who said it?")
@@ -26,7 +29,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
     let o = &cmd.data.options();
 
     let user = get_user(o, OPTION_USER)?;
-    let user = http.get_user(user.0.id).await?;
+    let user = http.get_user(user.0.id).counted("get_user").await?;
 
     if user == cmd.user {
         return Err(Error::Other("You cannot quote yourself"));
@@ -45,6 +48,29 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
 
     let message = CreateInteractionResponseMessage::new().embed(embed);
     cmd.create_response(http, CreateInteractionResponse::Message(message))
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+    fn cooldown(&self) -> Option<Cooldown> {
+        Some(COOLDOWN)
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+}
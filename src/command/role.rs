@@ -1,6 +1,7 @@
 use crate::prelude::*;
 
 pub const NAME: &str = "role";
+pub const DESCRIPTION: &str = "Create or manage role selectors";
 
 pub const BUTTON_TOGGLE: &str = formatcp!("{NAME}_toggle");
 
@@ -22,7 +23,7 @@ pub struct Toggle {
 #[async_trait]
 impl AsButtonAsync<GuildId> for Toggle {
     async fn as_button(&self, http: &Http, disabled: bool, guild: GuildId) -> Result<CreateButton> {
-        let mut roles = guild.roles(http).await?;
+        let mut roles = guild.roles(http).counted("get_guild_roles").await?;
         let Some(role) = roles.remove(&self.role) else {
 			return Err(Error::InvalidId(Value::Role, self.role.to_string()))
 		};
@@ -52,12 +53,26 @@ impl Selector {
     }
 }
 
+impl Versioned for Selector {
+    const MIGRATIONS: &'static [Migration] = &[legacy::<Self>];
+}
+
 impl NewReq<(GuildId, UserId)> for Selector {
     fn new_req((guild, user): (GuildId, UserId)) -> Req<Self> {
         Req::new(format!("{NAME}/{guild}"), user)
     }
 }
 
+impl NewReqDir<()> for Selector {
+    fn new_req_dir(_: ()) -> ReqDir<Self> {
+        ReqDir::new(NAME)
+    }
+
+    fn query(_: ()) -> Query<Self> {
+        Self::new_req_dir(()).query().deep()
+    }
+}
+
 impl AsReq<()> for Selector {
     fn as_req(&self, _: ()) -> Req<Self> {
         Self::new_req((self.guild, self.user))
@@ -67,7 +82,7 @@ impl AsReq<()> for Selector {
 pub fn new() -> CreateCommand {
     CreateCommand::new(NAME)
         .default_member_permissions(Permissions::MANAGE_ROLES)
-        .description("Create or manage role selectors")
+        .description(DESCRIPTION)
         .dm_permission(false)
         .add_option(
             CreateCommandOption::new(
@@ -100,10 +115,11 @@ pub fn new() -> CreateCommand {
             )
             .add_sub_option(
                 CreateCommandOption::new(
-                    CommandOptionType::Role,
+                    CommandOptionType::String,
                     OPTION_ROLE,
                     "The selector's linked role",
                 )
+                .set_autocomplete(true)
                 .required(true),
             ),
         )
@@ -137,13 +153,15 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
 	};
 
     let o = &cmd.data.options();
+    let _lock = Selector::new_req((guild, cmd.user.id)).lock().await?;
     let mut selector =
         Selector::read((guild, cmd.user.id)).unwrap_or_else(|_| Selector::new(cmd.user.id, guild));
 
     if let Ok(o) = get_subcommand(o, SUB_CREATE) {
         let role = get_role(o, OPTION_ROLE)?;
         let icon = get_str(o, OPTION_ICON).and_then(|s| {
-            ReactionType::try_from(s).map_err(|_| Error::InvalidId(Value::Role, s.to_string()))
+            ReactionType::try_from(s)
+                .map_err(|_| Error::InvalidInput(Value::Other("Emoji"), s.to_string()))
         })?;
 
         selector.roles.push(Toggle {
@@ -152,6 +170,14 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         });
         selector.write(())?;
 
+        let entry = audit::Entry::new(
+            cmd.user.id,
+            audit::Action::SelectorAdded,
+            audit::Target::Role(role.id),
+        );
+
+        audit::record(guild, entry).await;
+
         let title = format!("Created \"{}\" selector!", role.name);
         let embed = CreateEmbed::new().color(BOT_COLOR).title(title);
         let message = CreateInteractionResponseMessage::new()
@@ -159,21 +185,39 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if let Ok(o) = get_subcommand(o, SUB_REMOVE) {
-        let role = get_role(o, OPTION_ROLE)?;
-
-        selector.roles.retain(|t| t.role != role.id);
+        let role = RoleId::new(get_snowflake(o, OPTION_ROLE, Value::Role)?);
+        // the option resolves the role, so the name needs no request and a deleted role can still
+        // be removed by its id
+        let name = cmd
+            .data
+            .resolved
+            .roles
+            .get(&role)
+            .map_or_else(|| role.to_string(), |r| r.name.clone());
+
+        selector.roles.retain(|t| t.role != role);
         selector.write(())?;
 
-        let title = format!("Removed \"{}\" selector!", role.name);
+        let entry = audit::Entry::new(
+            cmd.user.id,
+            audit::Action::SelectorRemoved,
+            audit::Target::Role(role),
+        );
+
+        audit::record(guild, entry).await;
+
+        let title = format!("Removed \"{name}\" selector!");
         let embed = CreateEmbed::new().color(BOT_COLOR).title(title);
         let message = CreateInteractionResponseMessage::new()
             .embed(embed)
             .ephemeral(true);
 
         cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if get_subcommand(o, SUB_LIST).is_ok() {
@@ -189,6 +233,7 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         }
 
         cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
             .await
             .map_err(Error::from)
     } else if let Ok(o) = get_subcommand(o, SUB_SEND) {
@@ -197,30 +242,78 @@ pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
         }
 
         let title = get_str(o, OPTION_TEXT)?;
-        let embed = CreateEmbed::new().color(BOT_COLOR).title(title);
-        let mut message = CreateMessage::new().embed(embed);
+        let deferral = Deferral::Reply { ephemeral: true };
 
-        for toggle in &selector.roles {
-            let button = toggle.as_button(http, false, guild).await?;
+        cmd.defer_slow(http, deferral, async {
+            let embed = CreateEmbed::new().color(BOT_COLOR).title(title);
+            let mut message = CreateMessage::new().embed(embed);
 
-            message = message.button(button);
-        }
+            for toggle in &selector.roles {
+                let button = toggle.as_button(http, false, guild).await?;
+
+                message = message.button(button);
+            }
+
+            cmd.channel_id
+                .send_message(http, message)
+                .counted("create_message")
+                .await?;
+
+            Ok(())
+        })
+        .await?;
+
+        let roles = selector.roles.iter().map(|t| t.role).collect();
 
-        cmd.channel_id.send_message(http, message).await?;
         selector.remove(())?;
 
+        let entry = audit::Entry::new(
+            cmd.user.id,
+            audit::Action::SelectorSent,
+            audit::Target::Roles(roles),
+        );
+
+        audit::record(guild, entry).await;
+
         let embed = CreateEmbed::new().color(BOT_COLOR).title("Sent selectors!");
-        let message = CreateInteractionResponseMessage::new()
-            .embed(embed)
-            .ephemeral(true);
 
-        cmd.create_response(http, CreateInteractionResponse::Message(message))
-            .await
-            .map_err(Error::from)
+        cmd.reply(http, embed, true).await
     } else {
         Err(Error::InvalidId(Value::Command, cmd.data.name.clone()))
     }
 }
+pub async fn run_autocomplete(ctx: &Context, cmd: &CommandInteraction) -> Result<()> {
+    let guild = cmd.guild_id.ok_or(Error::MissingId(Value::Guild))?;
+    let focused = get_focused(cmd)?;
+
+    if focused != OPTION_ROLE {
+        return Err(Error::InvalidValue(Value::Data, focused.to_string()));
+    }
+
+    let Ok(selector) = Selector::read((guild, cmd.user.id)) else {
+        return suggest(&ctx.http, cmd, vec![]).await;
+    };
+
+    // autocomplete runs on every keystroke, so names come from the cache rather than a request
+    let choices = {
+        let cached = ctx.cache.guild(guild);
+
+        selector
+            .roles
+            .iter()
+            .map(|t| {
+                let name = cached
+                    .as_ref()
+                    .and_then(|g| g.roles.get(&t.role))
+                    .map_or_else(|| t.role.to_string(), |r| r.name.clone());
+
+                (name, t.role.to_string())
+            })
+            .collect()
+    };
+
+    suggest(&ctx.http, cmd, choices).await
+}
 pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
     let custom_id = CustomId::try_from(cpn.data.custom_id.as_str())?;
 
@@ -237,12 +330,86 @@ pub async fn run_component(http: &Http, cpn: &mut ComponentInteraction) -> Resul
     let role = RoleId::new(id);
 
     if member.roles.contains(&role) {
-        member.remove_role(http, role).await?;
+        member
+            .remove_role(http, role)
+            .counted("remove_member_role")
+            .await?;
     } else {
-        member.add_role(http, role).await?;
+        member
+            .add_role(http, role)
+            .counted("add_member_role")
+            .await?;
     }
 
     cpn.create_response(http, CreateInteractionResponse::Acknowledge)
+        .counted("create_interaction_response")
         .await
         .map_err(Error::from)
 }
+
+pub fn migrate(report: &mut MigrationReport) -> Result<()> {
+    for req in Selector::query(()).reqs()? {
+        report.record(&req);
+    }
+
+    Ok(())
+}
+
+pub fn purge(guild: GuildId) -> Result<()> {
+    storage().clear(&format!("{NAME}/{guild}"))
+}
+
+pub fn export(user: UserId, export: &mut data::Export) -> Result<()> {
+    let (selectors, failed) = Selector::query(())
+        .filter(move |s| s.user == user)
+        .read_with_errors()?;
+
+    for (req, selector) in selectors {
+        export.push(&req, "role_selector", &selector)?;
+    }
+
+    export.skip(&failed);
+
+    Ok(())
+}
+
+pub async fn forget(user: UserId) -> Result<usize> {
+    let mut count = 0;
+
+    for req in Selector::query(()).reqs()? {
+        let _lock = req.lock().await?;
+
+        // read under the lock, so that the record cannot change between the check and removal
+        if req.read().is_ok_and(|s| s.user == user) {
+            req.remove()?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+    async fn run_autocomplete(&self, ctx: &Context, cmd: &CommandInteraction) -> Result<()> {
+        run_autocomplete(ctx, cmd).await
+    }
+    async fn run_component(&self, http: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+        run_component(http, cpn).await
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serenity::all::Member;
+
+use crate::prelude::*;
+
+pub const NAME: &str = "settings";
+pub const DESCRIPTION: &str = "Manage guild settings";
+
+pub const SUB_VIEW: &str = "view";
+pub const SUB_RETENTION: &str = "retention";
+pub const SUB_LOG: &str = "log";
+pub const SUB_COOLDOWN: &str = "cooldown";
+pub const SUB_COMMANDS: &str = "commands";
+
+pub const OPTION_REPLIES: &str = "replies";
+pub const OPTION_RECORDS: &str = "records";
+pub const OPTION_CHANNEL: &str = "channel";
+pub const OPTION_COOLDOWN: &str = "cooldown";
+pub const OPTION_SECONDS: &str = "seconds";
+pub const OPTION_COMMAND: &str = "command";
+pub const OPTION_ENABLED: &str = "enabled";
+pub const OPTION_ROLE: &str = "role";
+pub const OPTION_RESET: &str = "reset";
+
+// both limits are opt-in, so closed polls keep everything until a guild sets them
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Retention {
+    // days after a poll closes before its individual replies are dropped
+    pub replies: Option<u32>,
+    // days after a poll closes before its record is removed entirely
+    pub records: Option<u32>,
+}
+
+impl Retention {
+    fn __days(days: Option<u32>) -> String {
+        days.map_or_else(|| "Forever".to_string(), |n| format!("{n} days"))
+    }
+}
+
+// empty role and channel lists place no restriction on the command
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    pub enabled: bool,
+    pub roles: BTreeSet<RoleId>,
+    pub channels: BTreeSet<ChannelId>,
+}
+
+impl Rules {
+    fn __toggle<T: Ord>(set: &mut BTreeSet<T>, value: T) {
+        if set.contains(&value) {
+            set.remove(&value);
+        } else {
+            set.insert(value);
+        }
+    }
+
+    // administrators are only bound by whether the command is enabled
+    pub fn permit(&self, channel: ChannelId, member: Option<&Member>) -> Result<()> {
+        if !self.enabled {
+            return Err(Error::Forbidden("This command is disabled in this guild"));
+        }
+
+        let permissions = member.and_then(|m| m.permissions).unwrap_or_default();
+
+        if permissions.administrator() {
+            return Ok(());
+        }
+        if !self.channels.is_empty() && !self.channels.contains(&channel) {
+            return Err(Error::Forbidden(
+                "This command cannot be used in this channel",
+            ));
+        }
+
+        let roles = member.map(|m| m.roles.as_slice()).unwrap_or_default();
+
+        if !self.roles.is_empty() && !roles.iter().any(|r| self.roles.contains(r)) {
+            return Err(Error::Forbidden(
+                "You do not have a role that may use this command",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            roles: BTreeSet::new(),
+            channels: BTreeSet::new(),
+        }
+    }
+}
+
+impl Display for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.enabled {
+            return write!(f, "Disabled");
+        }
+
+        let roles: Vec<_> = self.roles.iter().map(|r| format!("<@&{r}>")).collect();
+        let channels: Vec<_> = self.channels.iter().map(|c| format!("<#{c}>")).collect();
+        let roles = if roles.is_empty() {
+            "Any".to_string()
+        } else {
+            roles.join(", ")
+        };
+        let channels = if channels.is_empty() {
+            "Any".to_string()
+        } else {
+            channels.join(", ")
+        };
+
+        write!(f, "Roles: {roles}; Channels: {channels}")
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub retention: Retention,
+    // channel that receives the bot's warnings and errors for this guild
+    pub log: Option<ChannelId>,
+    // cooldown durations in seconds that replace the defaults, keyed by cooldown
+    pub cooldowns: BTreeMap<String, u64>,
+    // access rules for commands that differ from the defaults, keyed by command name
+    pub commands: BTreeMap<String, Rules>,
+}
+
+impl Versioned for Settings {}
+
+impl NewReq<GuildId> for Settings {
+    fn new_req(guild: GuildId) -> Req<Self> {
+        Req::new(NAME, guild)
+    }
+}
+
+impl NewReqDir<()> for Settings {
+    fn new_req_dir(_: ()) -> ReqDir<Self> {
+        ReqDir::new(NAME)
+    }
+}
+
+impl AsReq<GuildId> for Settings {
+    fn as_req(&self, guild: GuildId) -> Req<Self> {
+        Self::new_req(guild)
+    }
+}
+
+impl AsEmbed<()> for Settings {
+    fn as_embed(&self, _: ()) -> CreateEmbed {
+        let log = self
+            .log
+            .map_or_else(|| "None".to_string(), |c| format!("<#{c}>"));
+        let cooldowns = if self.cooldowns.is_empty() {
+            "Default".to_string()
+        } else {
+            self.cooldowns
+                .iter()
+                .map(|(k, n)| format!("`{k}` {n}s"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut description = format!(
+            "**Poll Replies:** {}\n**Poll Records:** {}\n**Log Channel:** {log}\n\
+             **Cooldowns:** {cooldowns}",
+            Retention::__days(self.retention.replies),
+            Retention::__days(self.retention.records),
+        );
+
+        for (name, rules) in &self.commands {
+            description.push_str(&format!("\n**/{name}:** {rules}"));
+        }
+
+        CreateEmbed::new()
+            .color(BOT_COLOR)
+            .description(description)
+            .title("Guild Settings")
+    }
+}
+
+pub fn new() -> CreateCommand {
+    CreateCommand::new(NAME)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .description(DESCRIPTION)
+        .dm_permission(false)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            SUB_VIEW,
+            "Displays the current guild settings",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                SUB_RETENTION,
+                "Configures how long closed poll data is kept",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    OPTION_REPLIES,
+                    "Days to keep individual poll replies (0 keeps them forever)",
+                )
+                .min_int_value(0),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    OPTION_RECORDS,
+                    "Days to keep closed poll results (0 keeps them forever)",
+                )
+                .min_int_value(0),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                SUB_LOG,
+                "Sets the channel that receives bot warnings and errors",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    OPTION_CHANNEL,
+                    "Log channel (leave empty to disable)",
+                )
+                .channel_types(vec![ChannelType::Text]),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                SUB_COOLDOWN,
+                "Overrides how long a command must wait between uses",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    OPTION_COOLDOWN,
+                    "The cooldown to override",
+                )
+                .set_autocomplete(true)
+                .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    OPTION_SECONDS,
+                    "Seconds between uses (0 disables it, leave empty to restore the default)",
+                )
+                .min_int_value(0)
+                .max_int_value(Cooldown::MAX_SECS),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                SUB_COMMANDS,
+                "Configures who may use a command and where",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    OPTION_COMMAND,
+                    "The command to configure",
+                )
+                .set_autocomplete(true)
+                .required(true),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                OPTION_ENABLED,
+                "Whether the command may be used at all",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Role,
+                OPTION_ROLE,
+                "Adds or removes a role that may use the command, within its default permissions",
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    OPTION_CHANNEL,
+                    "Adds or removes a channel that the command may be used in",
+                )
+                .channel_types(vec![ChannelType::Text]),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                OPTION_RESET,
+                "Whether to restore the command's default rules",
+            )),
+        )
+}
+
+// the settings command is exempt, so that it can never lock a guild out of its own rules
+pub fn permit(
+    name: &str,
+    guild: Option<GuildId>,
+    channel: ChannelId,
+    member: Option<&Member>,
+) -> Result<()> {
+    let Some(guild) = guild.filter(|_| name != NAME) else {
+        return Ok(());
+    };
+
+    match Settings::read(guild) {
+        Ok(settings) => settings
+            .commands
+            .get(name)
+            .map_or(Ok(()), |r| r.permit(channel, member)),
+        Err(error) if error.is_not_found() => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+// every cooldown that is declared by a registered command
+fn __cooldowns() -> impl Iterator<Item = Cooldown> {
+    REGISTRY
+        .iter()
+        .flat_map(|c| [c.cooldown(), c.component_cooldown()])
+        .flatten()
+}
+
+pub async fn run_command(http: &Http, cmd: &CommandInteraction) -> Result<()> {
+    let guild = cmd.guild_id.ok_or(Error::MissingId(Value::Guild))?;
+    let o = &cmd.data.options();
+
+    let _lock = Settings::new_req(guild).lock().await?;
+    let mut settings = Settings::read(guild).unwrap_or_default();
+
+    if get_subcommand(o, SUB_VIEW).is_ok() {
+        let message = CreateInteractionResponseMessage::new()
+            .embed(settings.as_embed(()))
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else if let Ok(o) = get_subcommand(o, SUB_RETENTION) {
+        let days = |n: i64| u32::try_from(n).ok().filter(|n| *n > 0);
+
+        if let Ok(n) = get_i64(o, OPTION_REPLIES) {
+            settings.retention.replies = days(n);
+        }
+        if let Ok(n) = get_i64(o, OPTION_RECORDS) {
+            settings.retention.records = days(n);
+        }
+
+        settings.write(guild)?;
+
+        let message = CreateInteractionResponseMessage::new()
+            .embed(settings.as_embed(()).title("Updated guild settings!"))
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else if let Ok(o) = get_subcommand(o, SUB_LOG) {
+        settings.log = get_channel(o, OPTION_CHANNEL).ok().map(|c| c.id);
+        settings.write(guild)?;
+
+        let message = CreateInteractionResponseMessage::new()
+            .embed(settings.as_embed(()).title("Updated guild settings!"))
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else if let Ok(o) = get_subcommand(o, SUB_COOLDOWN) {
+        let key = get_str(o, OPTION_COOLDOWN)?;
+
+        if !__cooldowns().any(|c| c.key == key) {
+            return Err(Error::InvalidValue(
+                Value::Other("Cooldown"),
+                key.to_string(),
+            ));
+        }
+
+        match get_i64(o, OPTION_SECONDS).map(u64::try_from) {
+            Ok(Ok(secs)) => settings.cooldowns.insert(key.to_string(), secs),
+            _ => settings.cooldowns.remove(key),
+        };
+
+        settings.write(guild)?;
+
+        let message = CreateInteractionResponseMessage::new()
+            .embed(settings.as_embed(()).title("Updated guild settings!"))
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else if let Ok(o) = get_subcommand(o, SUB_COMMANDS) {
+        let name = get_str(o, OPTION_COMMAND)?;
+
+        if name == NAME || REGISTRY.get(name).is_err() {
+            return Err(Error::InvalidInput(Value::Command, name.to_string()));
+        }
+
+        let mut rules = settings.commands.remove(name).unwrap_or_default();
+
+        if let Ok(enabled) = get_bool(o, OPTION_ENABLED) {
+            rules.enabled = enabled;
+        }
+        if let Ok(role) = get_role(o, OPTION_ROLE) {
+            Rules::__toggle(&mut rules.roles, role.id);
+        }
+        if let Ok(channel) = get_channel(o, OPTION_CHANNEL) {
+            Rules::__toggle(&mut rules.channels, channel.id);
+        }
+        if !get_bool(o, OPTION_RESET).unwrap_or(false) && rules != Rules::default() {
+            settings.commands.insert(name.to_string(), rules);
+        }
+
+        settings.write(guild)?;
+
+        let message = CreateInteractionResponseMessage::new()
+            .embed(settings.as_embed(()).title("Updated guild settings!"))
+            .ephemeral(true);
+
+        cmd.create_response(http, CreateInteractionResponse::Message(message))
+            .counted("create_interaction_response")
+            .await
+            .map_err(Error::from)
+    } else {
+        Err(Error::InvalidId(Value::Command, cmd.data.name.clone()))
+    }
+}
+pub async fn run_autocomplete(http: &Http, cmd: &CommandInteraction) -> Result<()> {
+    let choices = match get_focused(cmd)? {
+        OPTION_COOLDOWN => __cooldowns()
+            .map(|c| (c.to_string(), c.key.to_string()))
+            .collect(),
+        OPTION_COMMAND => REGISTRY
+            .iter()
+            .map(BotCommand::name)
+            .filter(|n| *n != NAME)
+            .map(|n| (format!("/{n}"), n.to_string()))
+            .collect(),
+        focused => return Err(Error::InvalidValue(Value::Data, focused.to_string())),
+    };
+
+    suggest(http, cmd, choices).await
+}
+
+pub fn migrate(report: &mut MigrationReport) -> Result<()> {
+    for req in Settings::query(()).reqs()? {
+        report.record(&req);
+    }
+
+    Ok(())
+}
+
+pub fn purge(guild: GuildId) -> Result<()> {
+    match Settings::new_req(guild).remove() {
+        Err(error) if !error.is_not_found() => Err(error),
+        _ => Ok(()),
+    }
+}
+
+pub struct Command;
+
+#[async_trait]
+impl BotCommand for Command {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn description(&self) -> &'static str {
+        DESCRIPTION
+    }
+    fn create(&self) -> CreateCommand {
+        new()
+    }
+
+    async fn run_command(&self, http: &Http, cmd: &CommandInteraction) -> Result<()> {
+        run_command(http, cmd).await
+    }
+    async fn run_autocomplete(&self, ctx: &Context, cmd: &CommandInteraction) -> Result<()> {
+        run_autocomplete(&ctx.http, cmd).await
+    }
+}
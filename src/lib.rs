@@ -0,0 +1,10 @@
+#![deny(clippy::expect_used, clippy::panic, clippy::unwrap_used)]
+#![warn(clippy::cargo, clippy::nursery, clippy::pedantic, clippy::try_err)]
+#![warn(clippy::todo, clippy::unimplemented, clippy::unreachable)]
+#![allow(clippy::module_name_repetitions, clippy::unused_async)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+#![allow(clippy::wildcard_imports)]
+
+pub mod command;
+pub mod prelude;
+pub mod utility;
@@ -5,11 +5,8 @@
 #![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
 #![allow(clippy::wildcard_imports)]
 
-use crate::prelude::*;
-
-mod command;
-mod prelude;
-mod utility;
+use mr_manager::{command, prelude::*};
+use serenity::{client::ClientBuilder, gateway::ShardManager};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -17,27 +14,194 @@ async fn main() -> Result<()> {
 
     let store = !flag("no-store");
     let enable = !flag("no-log");
-    let logger = Logger::new(store, enable)?;
+    let mirror = Mirror::default();
+    let logger = Logger::new(store, enable)?
+        .with_filter(LogFilter::from_env()?)
+        .with_mirror(mirror.clone())
+        .with_rotation(LogRotation::from_env()?)
+        .with_sink(LogSink::from_env()?);
+
+    init_logger(logger.clone())?;
+
+    init_storage(StorageKind::from_env()?, Format::from_env()?)?;
+
+    if let Some(path) = arg("restore") {
+        let (safety, count) = restore_backup(&path)?;
+
+        logger.info(format!("Backed up: {}", safety.display()))?;
+        logger.info(format!("Restored: {count} records from {path}"))?;
+    }
+
+    let report = command::migrate()?;
+    let storage = logger.target("storage");
 
-    let mut client = Client::builder(token()?, BOT_INTENTS)
+    for id in &report.migrated {
+        storage.debug(format!("Migrated: {id}"))?;
+    }
+    for (id, error) in &report.failed {
+        storage.warn(format!("Migration failed: {id} - {error}"))?;
+    }
+
+    storage.info(format!(
+        "Records: {} checked, {} migrated, {} failed",
+        report.checked,
+        report.migrated.len(),
+        report.failed.len()
+    ))?;
+
+    let mut client = ClientBuilder::new_with_http(new_http(&token()?), BOT_INTENTS)
         .event_handler(Handler::new(logger.clone()))
         .await?;
 
-    tokio::spawn(clock(logger));
-    client.start_autosharded().await.map_err(Error::from)
+    if let Some(server) = Server::from_env()? {
+        let logger = logger.target("server");
+
+        tokio::spawn(async move {
+            if let Err(error) = server.run(logger.clone()).await {
+                logger.error(format!("Server stopped: {error}")).ok();
+            }
+        });
+    }
+
+    let mut tasks = vec![
+        tokio::spawn(archive(logger.target(poll::NAME))),
+        tokio::spawn(flush(logger.target("mirror"), mirror.clone())),
+        tokio::spawn(clock(logger.clone())),
+    ];
+    let config = BackupConfig::from_env()?;
+
+    if config.is_enabled() {
+        tasks.push(tokio::spawn(backup(logger.target("backup"), config)));
+    }
+
+    tokio::spawn(stop(logger.clone(), Arc::clone(&client.shard_manager)));
+
+    let result = client.start_autosharded().await.map_err(Error::from);
+
+    // the client can also stop on its own, and the background tasks should end either way
+    shutdown().begin();
+
+    for task in tasks {
+        task.await.ok();
+    }
+
+    // anything that was queued while shutting down still gets posted
+    for (guild, error) in mirror.flush(&new_http(&token()?)).await {
+        logger.warn(format!("Mirroring failed: Guild<{guild}> - {error}"))?;
+    }
+
+    logger.info("Stopped")?;
+    logger.flush()?;
+
+    result
+}
+
+// stops accepting interactions once a signal arrives, then waits for in-flight work before
+// disconnecting every shard
+async fn stop(logger: Logger, shards: Arc<ShardManager>) -> Result<()> {
+    signal().await?;
+    logger.info("Shutting down")?;
+    shutdown().begin();
+
+    if !shutdown().drain(Shutdown::timeout()?).await {
+        logger.warn("Shutdown timed out with tasks still running")?;
+    }
+
+    shards.shutdown_all().await;
+
+    Ok(())
 }
 
 async fn clock(logger: Logger) -> Result<()> {
-    let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
-    let http = Arc::new(Http::new(&token()?));
+    let mut interval = tokio::time::interval(Health::CHECK_PERIOD);
+    let http = Arc::new(new_http(&token()?));
+    let guild = logger.target(Departed::DIR);
+    let logger = logger.target(poll::NAME);
+
+    while let Some(_task) = shutdown().tick(&mut interval).await {
+        match poll::check(&http).await {
+            Ok(failed) => {
+                health().checked();
+
+                for (guild, error) in failed {
+                    logger
+                        .guild(guild)
+                        .warn(format!("Closing failed: {error}"))?;
+                }
+            }
+            Err(error) => logger.warn(error.to_string())?,
+        }
 
-    loop {
-        interval.tick().await;
+        match purge_departed().await {
+            Ok(guilds) => {
+                for id in guilds {
+                    guild.info(format!("Purged: Guild<{id}>"))?;
+                }
+            }
+            Err(error) => guild.warn(error.to_string())?,
+        }
+    }
+
+    Ok(())
+}
 
-        let result = poll::check(&http).await;
+async fn archive(logger: Logger) -> Result<()> {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
 
-        if let Err(error) = result {
-            logger.warn(error.to_string())?;
+    while let Some(_task) = shutdown().tick(&mut interval).await {
+        let result = tokio::task::spawn_blocking(poll::archive)
+            .await
+            .unwrap_or_else(|_| Err(Error::Other("The archival task was interrupted")));
+
+        match result {
+            Ok((0, 0)) => {}
+            Ok((archived, removed)) => {
+                logger.info(format!("Polls: {archived} archived, {removed} removed"))?
+            }
+            Err(error) => logger.warn(format!("Archival failed: {error}"))?,
         }
     }
+
+    Ok(())
+}
+
+async fn flush(logger: Logger, mirror: Mirror) -> Result<()> {
+    let mut interval = tokio::time::interval(Mirror::period()?);
+    let http = Arc::new(new_http(&token()?));
+
+    while let Some(_task) = shutdown().tick(&mut interval).await {
+        // failures are logged without a guild so they are never mirrored back into the queue
+        for (guild, error) in mirror.flush(&http).await {
+            logger.warn(format!("Mirroring failed: Guild<{guild}> - {error}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn backup(logger: Logger, config: BackupConfig) -> Result<()> {
+    let start = tokio::time::Instant::now() + config.period();
+    let mut interval = tokio::time::interval_at(start, config.period());
+
+    while let Some(_task) = shutdown().tick(&mut interval).await {
+        // archiving reads every record, so it runs off the async workers
+        let result = tokio::task::spawn_blocking(create_backup)
+            .await
+            .unwrap_or_else(|_| Err(Error::Other("The backup task was interrupted")));
+
+        match result {
+            Ok(path) => logger.info(format!("Backed up: {}", path.display()))?,
+            Err(error) => logger.warn(format!("Backup failed: {error}"))?,
+        }
+        match config.prune() {
+            Ok(paths) => {
+                for path in paths {
+                    logger.info(format!("Pruned: {}", path.display()))?;
+                }
+            }
+            Err(error) => logger.warn(format!("Pruning failed: {error}"))?,
+        }
+    }
+
+    Ok(())
 }
@@ -17,5 +17,7 @@ pub use serenity::{
 
 pub use crate::command::*;
 pub use crate::utility::{
-    anchor::*, custom_id::*, formatting::*, handler::*, logger::*, req::*, traits::*, *,
+    anchor::*, backup::*, cooldown::*, custom_id::*, defer::*, format::*, formatting::*,
+    handler::*, health::*, lifecycle::*, logger::*, metrics::*, migrate::*, mirror::*, paging::*,
+    registry::*, req::*, server::*, shutdown::*, storage::*, traits::*, *,
 };
@@ -3,11 +3,25 @@ use serenity::prelude::GatewayIntents;
 use crate::prelude::*;
 
 pub mod anchor;
+pub mod backup;
+pub mod cooldown;
 pub mod custom_id;
+pub mod defer;
+pub mod format;
 pub mod formatting;
 pub mod handler;
+pub mod health;
+pub mod lifecycle;
 pub mod logger;
+pub mod metrics;
+pub mod migrate;
+pub mod mirror;
+pub mod paging;
+pub mod registry;
 pub mod req;
+pub mod server;
+pub mod shutdown;
+pub mod storage;
 pub mod traits;
 
 #[cfg(debug_assertions)]
@@ -44,6 +58,30 @@ pub enum Value {
     Other(&'static str),
 }
 
+impl Value {
+    // how the value is referred to in messages that users read
+    pub fn label(&self) -> String {
+        let text = match self {
+            Self::Anchor => "message link",
+            Self::Channel => "channel",
+            Self::Command => "command",
+            Self::Component => "component",
+            Self::CustomId => "component id",
+            Self::Data => "value",
+            Self::Guild => "server",
+            Self::Interaction => "interaction",
+            Self::Member => "member",
+            Self::Message => "message",
+            Self::Modal => "form",
+            Self::Role => "role",
+            Self::User => "user",
+            Self::Other(s) => return s.to_lowercase(),
+        };
+
+        text.to_string()
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -59,12 +97,18 @@ pub enum Error {
     Decode(rmp_serde::decode::Error),
     Encode(rmp_serde::encode::Error),
     Serenity(serenity::Error),
+    Database(rusqlite::Error),
+    Json(serde_json::Error),
 
     InvalidId(Value, String),
     InvalidValue(Value, String),
     MissingId(Value),
     MissingValue(Value),
+    // a value that the user typed in, as opposed to one the bot produced or stored
+    InvalidInput(Value, String),
 
+    Cooldown(TimeString),
+    Forbidden(&'static str),
     Other(&'static str),
 }
 
@@ -82,6 +126,53 @@ from!(std::io::Error => Self::ReadWrite);
 from!(rmp_serde::decode::Error => Self::Decode);
 from!(rmp_serde::encode::Error => Self::Encode);
 from!(serenity::Error => Self::Serenity);
+from!(rusqlite::Error => Self::Database);
+from!(serde_json::Error => Self::Json);
+
+impl Error {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::ReadWrite(e) if e.kind() == std::io::ErrorKind::NotFound)
+    }
+    // mistakes in a user's input and messages written for users; anything else, including bad
+    // custom ids and stored data, is an internal failure
+    pub const fn is_user_facing(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidInput(..) | Self::Cooldown(_) | Self::Forbidden(_) | Self::Other(_)
+        )
+    }
+    // what the user is shown, where `Display` is written for the logs; internal failures are never
+    // described, since their details may include ids and paths
+    pub fn message(&self) -> String {
+        match self {
+            Self::InvalidInput(k, s) => format!("\"{}\" is not a valid {}", s.trim(), k.label()),
+            _ if !self.is_user_facing() => "Something went wrong on our end.".to_string(),
+            error => error.to_string(),
+        }
+    }
+    // a short code that is shown in place of an internal failure and logged alongside its details
+    pub fn reference() -> String {
+        format!("{:08X}", rand::random::<u32>())
+    }
+    pub const fn variant(&self) -> &'static str {
+        match self {
+            Self::ReadWrite(_) => "ReadWrite",
+            Self::Decode(_) => "Decode",
+            Self::Encode(_) => "Encode",
+            Self::Serenity(_) => "Serenity",
+            Self::Database(_) => "Database",
+            Self::Json(_) => "Json",
+            Self::InvalidId(..) => "InvalidId",
+            Self::InvalidValue(..) => "InvalidValue",
+            Self::MissingId(_) => "MissingId",
+            Self::MissingValue(_) => "MissingValue",
+            Self::InvalidInput(..) => "InvalidInput",
+            Self::Cooldown(_) => "Cooldown",
+            Self::Forbidden(_) => "Forbidden",
+            Self::Other(_) => "Other",
+        }
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -90,13 +181,17 @@ impl Display for Error {
             Self::Decode(e) => return e.fmt(f),
             Self::Encode(e) => return e.fmt(f),
             Self::Serenity(e) => return e.fmt(f),
+            Self::Database(e) => return e.fmt(f),
+            Self::Json(e) => return e.fmt(f),
 
             Self::MissingId(k) => format!("Missing identifier: {k}<?>"),
             Self::InvalidId(k, s) => format!("Invalid identifier: {k}<{s}>"),
             Self::MissingValue(k) => format!("Missing value: {k}<?>"),
             Self::InvalidValue(k, s) => format!("Invalid value: {k}<{s}>"),
+            Self::InvalidInput(k, s) => format!("Invalid input: {k}<{s}>"),
 
-            Self::Other(s) => (*s).to_string(),
+            Self::Cooldown(t) => format!("You can use this again {t}"),
+            Self::Forbidden(s) | Self::Other(s) => (*s).to_string(),
         };
 
         write!(f, "{text}")
@@ -108,18 +203,73 @@ pub fn flag(name: &str) -> bool {
 
     std::env::args().any(|f| f == flag)
 }
+pub fn arg(name: &str) -> Option<String> {
+    let flag = format!("--{name}");
+    let mut args = std::env::args().skip_while(|f| *f != flag);
+
+    args.next().and_then(|_| args.next())
+}
+pub fn env_var<T: std::str::FromStr>(key: &'static str, default: T) -> Result<T> {
+    std::env::var(key).map_or(Ok(default), |raw| {
+        raw.parse()
+            .map_err(|_| Error::InvalidValue(Value::Other(key), raw))
+    })
+}
 pub fn token() -> Result<String> {
     let key = if IS_DEV { "DEV_TOKEN" } else { "TOKEN" };
 
     std::env::var(key).map_err(|_| Error::MissingValue(Value::Other("Token")))
 }
-pub fn dev_guild() -> Result<GuildId> {
-    let Ok(raw) = std::env::var("DEV_GUILD") else {
-		return Err(Error::MissingId(Value::Guild));
-	};
-    let Ok(id) = raw.parse() else {
-		return Err(Error::InvalidId(Value::Guild, raw));
-	};
-
-    Ok(GuildId::new(id))
+// a comma-separated list of test guilds, falling back to the single guild of older setups
+pub fn dev_guilds() -> Result<Vec<GuildId>> {
+    let Ok(raw) = std::env::var("DEV_GUILDS").or_else(|_| std::env::var("DEV_GUILD")) else {
+        return Err(Error::MissingId(Value::Guild));
+    };
+
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse() {
+            Ok(id) if id != 0 => Ok(GuildId::new(id)),
+            _ => Err(Error::InvalidId(Value::Guild, s.to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_input_errors_are_user_facing() {
+        let not_found = Error::ReadWrite(std::io::Error::from(std::io::ErrorKind::NotFound));
+
+        assert!(Error::InvalidInput(Value::User, String::new()).is_user_facing());
+        assert!(Error::Other("Some message").is_user_facing());
+        assert!(!not_found.is_user_facing());
+        assert!(!Error::MissingValue(Value::Other("Entry")).is_user_facing());
+        assert!(!Error::InvalidValue(Value::Data, String::new()).is_user_facing());
+    }
+
+    #[test]
+    fn messages_are_readable() {
+        assert_eq!(
+            Error::InvalidInput(Value::Other("Emoji"), " :x ".to_string()).message(),
+            "\":x\" is not a valid emoji"
+        );
+        assert_eq!(Error::Forbidden("Not allowed").message(), "Not allowed");
+    }
+
+    #[test]
+    fn component_ids_are_not_shown() {
+        let raw = "poll_choice;123;4.567";
+
+        for error in [
+            Error::InvalidValue(Value::Component, raw.to_string()),
+            Error::InvalidId(Value::CustomId, raw.to_string()),
+        ] {
+            assert!(!error.is_user_facing());
+            assert!(!error.message().contains(raw));
+        }
+    }
 }
@@ -11,11 +11,15 @@ impl Anchor {
     const __URL: &str = "https://discord.com/channels";
 
     pub async fn to_guild(self, http: &Http) -> Result<PartialGuild> {
-        Ok(self.guild.to_partial_guild(http).await?)
+        Ok(self
+            .guild
+            .to_partial_guild(http)
+            .counted("get_guild")
+            .await?)
     }
     pub async fn to_channel(self, http: &Http) -> Result<GuildChannel> {
         let guild = self.to_guild(http).await?;
-        let mut list = guild.channels(http).await?;
+        let mut list = guild.channels(http).counted("get_channels").await?;
 
         list.remove(&self.channel)
             .ok_or_else(|| Error::InvalidId(Value::Channel, self.guild.to_string()))
@@ -23,7 +27,10 @@ impl Anchor {
     pub async fn to_message(self, http: &Http) -> Result<Message> {
         let channel = self.to_channel(http).await?;
 
-        Ok(channel.message(http, self.message).await?)
+        Ok(channel
+            .message(http, self.message)
+            .counted("get_message")
+            .await?)
     }
 }
 
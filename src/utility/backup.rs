@@ -0,0 +1,302 @@
+use std::{
+    fs::{create_dir_all, read_dir, remove_file, rename, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::prelude::*;
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub dir: String,
+    pub key: String,
+    pub raw: Vec<u8>,
+}
+
+impl Record {
+    pub fn path(&self) -> String {
+        let ext = Format::detect(&self.raw).ext();
+
+        format!("{}/{}.{ext}", self.dir, self.key)
+    }
+
+    // archive paths are untrusted, so anything outside of a plain `dir/key.ext` layout is refused
+    pub fn from_path(path: &str, raw: Vec<u8>) -> Result<Self> {
+        let invalid = || Error::InvalidValue(Value::Other("Archive path"), path.to_string());
+        let (dir, file) = path.rsplit_once('/').ok_or_else(invalid)?;
+        let (key, ext) = file.rsplit_once('.').ok_or_else(invalid)?;
+
+        if Format::from_ext(ext).is_none()
+            || key.is_empty()
+            || dir
+                .split('/')
+                .any(|s| s.is_empty() || s == "." || s == "..")
+        {
+            return Err(invalid());
+        }
+
+        let (dir, key) = (dir.to_string(), key.to_string());
+
+        Ok(Self { dir, key, raw })
+    }
+
+    // decodes the record as its stored type, re-encoding it in the given format
+    pub fn recode(&self, format: Format) -> Result<Vec<u8>> {
+        crate::command::recode(&self.dir, &self.key, &self.raw, format)
+    }
+}
+
+// collects every record stored within `dir` and its subdirectories
+pub fn records(dir: &str) -> Result<Vec<Record>> {
+    records_in(storage(), dir)
+}
+pub fn records_in(store: &dyn Storage, dir: &str) -> Result<Vec<Record>> {
+    let mut found = vec![];
+
+    for key in store.keys(dir)? {
+        let raw = store.read(dir, &key)?;
+
+        found.push(Record {
+            dir: dir.to_string(),
+            key,
+            raw,
+        });
+    }
+    for child in store.dirs(dir)? {
+        let path = if dir.is_empty() {
+            child
+        } else {
+            format!("{dir}/{child}")
+        };
+
+        found.append(&mut records_in(store, &path)?);
+    }
+
+    Ok(found)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BackupConfig {
+    pub hours: u64,
+    pub retain: usize,
+}
+
+impl BackupConfig {
+    pub const DIR: &str = "backups";
+    pub const EXT: &str = "tar.gz";
+    pub const PREFIX: &str = "data_";
+
+    pub const KEY_HOURS: &str = "BACKUP_INTERVAL_HOURS";
+    pub const KEY_RETAIN: &str = "BACKUP_RETAIN";
+
+    pub fn from_env() -> Result<Self> {
+        let retain = env_var(Self::KEY_RETAIN, 7)?;
+
+        // retaining nothing would delete the archive that was just created
+        if retain == 0 {
+            return Err(Error::InvalidValue(
+                Value::Other(Self::KEY_RETAIN),
+                retain.to_string(),
+            ));
+        }
+
+        Ok(Self {
+            hours: env_var(Self::KEY_HOURS, 24)?,
+            retain,
+        })
+    }
+
+    pub const fn is_enabled(self) -> bool {
+        self.hours > 0
+    }
+    pub const fn period(self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.hours * 60 * 60)
+    }
+
+    // returns every archive within the backup directory, oldest first
+    pub fn archives() -> Result<Vec<PathBuf>> {
+        let entries = match read_dir(Self::DIR) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+        let mut archives = vec![];
+
+        for entry in entries {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            if name.starts_with(Self::PREFIX) && name.ends_with(Self::EXT) {
+                archives.push(path);
+            }
+        }
+
+        // names embed their creation time, so lexical order is chronological
+        archives.sort();
+
+        Ok(archives)
+    }
+
+    pub fn prune(self) -> Result<Vec<PathBuf>> {
+        let mut archives = Self::archives()?;
+        let count = archives.len().saturating_sub(self.retain.max(1));
+        let pruned: Vec<_> = archives.drain(..count).collect();
+
+        for path in &pruned {
+            remove_file(path)?;
+        }
+
+        Ok(pruned)
+    }
+}
+
+pub fn create_backup() -> Result<PathBuf> {
+    let name = Utc::now().format("%y%m%d_%H%M%S_%6f").to_string();
+    let path = Path::new(BackupConfig::DIR).join(format!(
+        "{}{name}.{}",
+        BackupConfig::PREFIX,
+        BackupConfig::EXT
+    ));
+    let temp = path.with_extension("tmp");
+
+    create_dir_all(BackupConfig::DIR)?;
+
+    let file = File::create(&temp)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let time = u64::try_from(Utc::now().timestamp()).unwrap_or_default();
+
+    for record in records("")? {
+        let mut header = tar::Header::new_gnu();
+
+        header.set_size(record.raw.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(time);
+        header.set_cksum();
+
+        archive.append_data(&mut header, record.path(), record.raw.as_slice())?;
+    }
+
+    archive.into_inner()?.finish()?.sync_all()?;
+    rename(&temp, &path)?;
+
+    Ok(path)
+}
+
+pub fn read_backup(path: impl AsRef<Path>) -> Result<Vec<Record>> {
+    let file = File::open(path)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut records = vec![];
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_string_lossy().to_string();
+        let mut raw = vec![];
+
+        entry.read_to_end(&mut raw)?;
+
+        let record = Record::from_path(&path, raw)?;
+
+        // every record must decode as its stored type before anything is replaced
+        if let Err(error) = record.recode(Format::detect(&record.raw)) {
+            return Err(Error::InvalidValue(
+                Value::Other("Archived record"),
+                format!("{path} - {error}"),
+            ));
+        }
+
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+// replaces the entire store with the archive's contents, returning the safety backup's path
+pub fn restore_backup(path: impl AsRef<Path>) -> Result<(PathBuf, usize)> {
+    let records = read_backup(path)?;
+    let safety = create_backup()?;
+
+    storage().clear("")?;
+
+    for record in &records {
+        let raw = record.recode(storage_format())?;
+
+        storage().write(&record.dir, &record.key, &raw)?;
+    }
+
+    Ok((safety, records.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    // the tar builder refuses to write unsafe paths, so the name is set on the raw header
+    fn archive_with(name: &str) -> Result<PathBuf> {
+        let path = std::env::temp_dir().join(format!("mrm_backup_{}.tar.gz", std::process::id()));
+        let file = File::create(&path)?;
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        let raw = b"{}";
+
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(raw.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        archive.append(&header, raw.as_slice())?;
+        archive.into_inner()?.finish()?.flush()?;
+
+        Ok(path)
+    }
+
+    #[test]
+    fn plain_paths_are_accepted() -> Result<()> {
+        let record = Record::from_path("poll/1/2.json", vec![])?;
+
+        assert_eq!((record.dir.as_str(), record.key.as_str()), ("poll/1", "2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn escaping_paths_are_refused() {
+        for path in [
+            "../poll/2.json",
+            "poll/../../2.json",
+            "poll/./2.json",
+            "/poll/2.json",
+            "poll//2.json",
+            "2.json",
+            "poll/.json",
+            "poll/2.txt",
+        ] {
+            assert!(Record::from_path(path, vec![]).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn escaping_archives_are_not_read() -> Result<()> {
+        let path = archive_with("../poll/1/2.json")?;
+        let result = read_backup(&path);
+
+        remove_file(path)?;
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidValue(Value::Other("Archive path"), _))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::prelude::*;
+
+static COOLDOWNS: OnceLock<Cooldowns> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bucket {
+    User,
+    Channel,
+    Guild,
+}
+
+impl Display for Bucket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::User => "per user",
+            Self::Channel => "per channel",
+            Self::Guild => "per guild",
+        };
+
+        write!(f, "{text}")
+    }
+}
+
+// a command's default cooldown; guilds may override the duration through their settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cooldown {
+    pub key: &'static str,
+    pub bucket: Bucket,
+    pub secs: u64,
+}
+
+impl Cooldown {
+    pub const MAX_SECS: u64 = 86_400;
+
+    pub const fn new(key: &'static str, bucket: Bucket, secs: u64) -> Self {
+        Self { key, bucket, secs }
+    }
+
+    // an unreadable settings record should not lock everyone out of the command, so the default
+    // duration applies until it is fixed
+    fn __secs(self, guild: Option<GuildId>) -> u64 {
+        let Some(guild) = guild else {
+            return self.secs;
+        };
+
+        match settings::Settings::read(guild) {
+            Ok(settings) => settings
+                .cooldowns
+                .get(self.key)
+                .copied()
+                .unwrap_or(self.secs),
+            Err(error) if error.is_not_found() => self.secs,
+            Err(error) => {
+                let message = format!("Cooldown settings unreadable: Guild<{guild}> - {error}");
+
+                if let Some(logger) = logger() {
+                    logger.warn(message).ok();
+                }
+
+                self.secs
+            }
+        }
+    }
+}
+
+impl Display for Cooldown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}s {})", self.key, self.secs, self.bucket)
+    }
+}
+
+// the time at which each bucket may next be used; this is not persisted across restarts
+#[derive(Debug, Default)]
+pub struct Cooldowns(Mutex<BTreeMap<(&'static str, Bucket, u64), DateTime<Utc>>>);
+
+impl Cooldowns {
+    fn __key(
+        cooldown: Cooldown,
+        guild: Option<GuildId>,
+        channel: ChannelId,
+        user: UserId,
+    ) -> (&'static str, Bucket, u64) {
+        // guild buckets fall back to the channel in direct messages
+        let id = match cooldown.bucket {
+            Bucket::User => user.get(),
+            Bucket::Channel => channel.get(),
+            Bucket::Guild => guild.map_or_else(|| channel.get(), GuildId::get),
+        };
+
+        (cooldown.key, cooldown.bucket, id)
+    }
+
+    // starts the cooldown if the bucket is free, otherwise fails with the time that it frees up
+    pub fn check(
+        &self,
+        cooldown: Option<Cooldown>,
+        guild: Option<GuildId>,
+        channel: ChannelId,
+        user: UserId,
+    ) -> Result<()> {
+        let Some(cooldown) = cooldown else {
+            return Ok(());
+        };
+
+        let secs = cooldown.__secs(guild);
+
+        if secs == 0 {
+            return Ok(());
+        }
+
+        let key = Self::__key(cooldown, guild, channel, user);
+        let now = Utc::now();
+        let Ok(mut map) = self.0.lock() else {
+            return Err(Error::Other("Cooldowns are unavailable"));
+        };
+
+        map.retain(|_, until| *until > now);
+
+        if let Some(until) = map.get(&key) {
+            return Err(Error::Cooldown(TimeString::from(*until)));
+        }
+
+        let secs = i64::try_from(secs.min(Cooldown::MAX_SECS)).unwrap_or_default();
+
+        map.insert(key, now + chrono::Duration::seconds(secs));
+
+        Ok(())
+    }
+    // frees a bucket that was started by a run which then failed
+    pub fn refund(
+        &self,
+        cooldown: Option<Cooldown>,
+        guild: Option<GuildId>,
+        channel: ChannelId,
+        user: UserId,
+    ) {
+        let Some(cooldown) = cooldown else {
+            return;
+        };
+
+        if let Ok(mut map) = self.0.lock() {
+            map.remove(&Self::__key(cooldown, guild, channel, user));
+        }
+    }
+}
+
+pub fn cooldowns() -> &'static Cooldowns {
+    COOLDOWNS.get_or_init(Cooldowns::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: Cooldown = Cooldown::new("test_user", Bucket::User, 60);
+    const CHANNEL: Cooldown = Cooldown::new("test_channel", Bucket::Channel, 60);
+
+    #[test]
+    fn check_starts_and_blocks() {
+        let cooldowns = Cooldowns::default();
+        let (channel, user) = (ChannelId::new(1), UserId::new(2));
+
+        assert!(cooldowns.check(Some(USER), None, channel, user).is_ok());
+        assert!(matches!(
+            cooldowns.check(Some(USER), None, channel, user),
+            Err(Error::Cooldown(_))
+        ));
+    }
+
+    #[test]
+    fn check_separates_buckets() {
+        let cooldowns = Cooldowns::default();
+        let check = |cooldown, channel, user| {
+            cooldowns.check(
+                Some(cooldown),
+                None,
+                ChannelId::new(channel),
+                UserId::new(user),
+            )
+        };
+
+        assert!(check(USER, 1, 2).is_ok());
+        assert!(check(USER, 1, 3).is_ok());
+        assert!(check(CHANNEL, 1, 2).is_ok());
+        assert!(check(CHANNEL, 1, 3).is_err());
+        assert!(check(CHANNEL, 4, 3).is_ok());
+    }
+
+    #[test]
+    fn check_skips_disabled() {
+        let cooldowns = Cooldowns::default();
+        let off = Cooldown::new("test_off", Bucket::User, 0);
+        let (channel, user) = (ChannelId::new(1), UserId::new(2));
+
+        for _ in 0..2 {
+            assert!(cooldowns.check(None, None, channel, user).is_ok());
+            assert!(cooldowns.check(Some(off), None, channel, user).is_ok());
+        }
+    }
+
+    #[test]
+    fn refund_frees_bucket() {
+        let cooldowns = Cooldowns::default();
+        let (channel, user) = (ChannelId::new(1), UserId::new(2));
+
+        assert!(cooldowns.check(Some(USER), None, channel, user).is_ok());
+        cooldowns.refund(Some(USER), None, channel, user);
+        assert!(cooldowns.check(Some(USER), None, channel, user).is_ok());
+    }
+}
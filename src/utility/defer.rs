@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use crate::prelude::*;
+
+static DEFERRALS: OnceLock<Deferrals> = OnceLock::new();
+
+// discord only waits three seconds for the first response, so this leaves room for the request
+pub const DEFER_AFTER: Duration = Duration::from_millis(1500);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deferral {
+    // a loading reply, which is edited once the work is done
+    Reply { ephemeral: bool },
+    // an acknowledgement that leaves the interaction's message as it is
+    Update,
+}
+
+// the interactions that have been deferred and not yet handled
+#[derive(Debug, Default)]
+pub struct Deferrals(Mutex<BTreeMap<InteractionId, Deferral>>);
+
+impl Deferrals {
+    pub fn get(&self, id: InteractionId) -> Option<Deferral> {
+        self.0.lock().ok()?.get(&id).copied()
+    }
+    pub fn insert(&self, id: InteractionId, deferral: Deferral) {
+        if let Ok(mut map) = self.0.lock() {
+            map.insert(id, deferral);
+        }
+    }
+    pub fn remove(&self, id: InteractionId) -> Option<Deferral> {
+        self.0.lock().ok()?.remove(&id)
+    }
+}
+
+pub fn deferrals() -> &'static Deferrals {
+    DEFERRALS.get_or_init(Deferrals::default)
+}
+
+#[async_trait]
+pub trait Defer: Sync {
+    fn interaction(&self) -> InteractionId;
+
+    async fn __defer(&self, http: &Http, deferral: Deferral) -> Result<()>;
+    async fn __edit(&self, http: &Http, embed: CreateEmbed) -> Result<()>;
+    async fn __followup(&self, http: &Http, embed: CreateEmbed, ephemeral: bool) -> Result<()>;
+    async fn __respond(&self, http: &Http, response: CreateInteractionResponse) -> Result<()>;
+
+    fn deferral(&self) -> Option<Deferral> {
+        deferrals().get(self.interaction())
+    }
+
+    // the part of the deferral window that is left, counted from the interaction's creation
+    fn remaining(&self) -> Duration {
+        let created = self.interaction().created_at().timestamp_millis();
+        let elapsed = u64::try_from(Utc::now().timestamp_millis() - created).unwrap_or_default();
+
+        DEFER_AFTER.saturating_sub(Duration::from_millis(elapsed))
+    }
+
+    // runs the work, deferring the interaction if it has not finished in time to respond; a failed
+    // deferral is only logged, since the work carries on regardless and its result matters more
+    async fn defer_slow<T, F>(&self, http: &Http, deferral: Deferral, work: F) -> Result<T>
+    where
+        T: Send,
+        F: Future<Output = Result<T>> + Send,
+    {
+        tokio::pin!(work);
+
+        tokio::select! {
+            result = &mut work => return result,
+            () = tokio::time::sleep(self.remaining()) => {}
+        }
+
+        let (deferred, result) = tokio::join!(self.__defer(http, deferral), work);
+
+        if let (Err(error), Some(logger)) = (deferred, logger()) {
+            logger
+                .warn(format!("Deferral failed: {} - {error}", self.interaction()))
+                .ok();
+        }
+
+        result
+    }
+
+    // replies with the embed, editing the loading reply if the interaction was deferred
+    async fn reply(&self, http: &Http, embed: CreateEmbed, ephemeral: bool) -> Result<()> {
+        match self.deferral() {
+            Some(Deferral::Reply { .. }) => self.__edit(http, embed).await,
+            Some(Deferral::Update) => self.__followup(http, embed, ephemeral).await,
+            None => {
+                let message = CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .ephemeral(ephemeral);
+
+                self.__respond(http, CreateInteractionResponse::Message(message))
+                    .await
+            }
+        }
+    }
+    // acknowledges the interaction, unless that already happened when it was deferred
+    async fn acknowledge(&self, http: &Http) -> Result<()> {
+        if self.deferral().is_some() {
+            return Ok(());
+        }
+
+        self.__respond(http, CreateInteractionResponse::Acknowledge)
+            .await
+    }
+}
+
+macro_rules! defer {
+    ($($kind:ty),+) => {$(
+        #[async_trait]
+        impl Defer for $kind {
+            fn interaction(&self) -> InteractionId {
+                self.id
+            }
+
+            async fn __defer(&self, http: &Http, deferral: Deferral) -> Result<()> {
+                let response = match deferral {
+                    Deferral::Reply { ephemeral } => CreateInteractionResponse::Defer(
+                        CreateInteractionResponseMessage::new().ephemeral(ephemeral),
+                    ),
+                    Deferral::Update => CreateInteractionResponse::Acknowledge,
+                };
+
+                self.create_response(http, response).counted("create_interaction_response").await?;
+                deferrals().insert(self.id, deferral);
+
+                Ok(())
+            }
+            async fn __edit(&self, http: &Http, embed: CreateEmbed) -> Result<()> {
+                let edit = EditInteractionResponse::new().embed(embed);
+
+                self.edit_response(http, edit).counted("edit_interaction_response").await?;
+
+                Ok(())
+            }
+            async fn __followup(
+                &self,
+                http: &Http,
+                embed: CreateEmbed,
+                ephemeral: bool,
+            ) -> Result<()> {
+                let followup = CreateInteractionResponseFollowup::new()
+                    .embed(embed)
+                    .ephemeral(ephemeral);
+
+                self.create_followup(http, followup).counted("create_followup_message").await?;
+
+                Ok(())
+            }
+            async fn __respond(
+                &self,
+                http: &Http,
+                response: CreateInteractionResponse,
+            ) -> Result<()> {
+                self.create_response(http, response).counted("create_interaction_response")
+                    .await
+                    .map_err(Error::from)
+            }
+        }
+    )+};
+}
+
+defer!(CommandInteraction, ComponentInteraction, ModalInteraction);
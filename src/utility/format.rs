@@ -0,0 +1,118 @@
+use std::sync::OnceLock;
+
+use serde::de::DeserializeOwned;
+
+use crate::prelude::*;
+
+static FORMAT: OnceLock<Format> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Rmp,
+    Json,
+}
+
+impl Format {
+    pub const KEY: &str = "STORAGE_FORMAT";
+    pub const ALL: [Self; 2] = [Self::Rmp, Self::Json];
+
+    pub fn from_env() -> Result<Self> {
+        std::env::var(Self::KEY).map_or_else(|_| Ok(Self::default()), Self::try_from)
+    }
+
+    pub const fn ext(self) -> &'static str {
+        match self {
+            Self::Rmp => "rmp",
+            Self::Json => "json",
+        }
+    }
+    pub fn from_ext(ext: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.ext() == ext)
+    }
+
+    // a brace or bracket is also a valid message pack integer, but stored records are always maps
+    // or arrays, whose message pack encodings start at or above 0x80
+    pub fn detect(raw: &[u8]) -> Self {
+        match raw.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{' | b'[') => Self::Json,
+            _ => Self::Rmp,
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Self::Rmp => Ok(rmp_serde::to_vec_named(value)?),
+            Self::Json => Ok(serde_json::to_vec_pretty(value)?),
+        }
+    }
+    pub fn decode<T: DeserializeOwned>(self, raw: &[u8]) -> Result<T> {
+        match self {
+            Self::Rmp => Ok(rmp_serde::from_slice(raw)?),
+            Self::Json => Ok(serde_json::from_slice(raw)?),
+        }
+    }
+}
+
+impl TryFrom<String> for Format {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "rmp" | "msgpack" => Ok(Self::Rmp),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::InvalidValue(Value::Other("Format"), value)),
+        }
+    }
+}
+
+pub fn init_format(format: Format) -> Result<()> {
+    FORMAT
+        .set(format)
+        .map_err(|_| Error::Other("Format has already been initialized"))
+}
+pub fn storage_format() -> Format {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn detect_encoded_records() -> Result<()> {
+        let map = BTreeMap::from([("key", 1)]);
+        let list = vec![1, 2, 3];
+
+        for format in Format::ALL {
+            assert_eq!(Format::detect(&format.encode(&map)?), format);
+            assert_eq!(Format::detect(&format.encode(&list)?), format);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect_json_after_whitespace() {
+        assert_eq!(Format::detect(b"\n  {}"), Format::Json);
+        assert_eq!(Format::detect(b"\t[]"), Format::Json);
+        assert_eq!(Format::detect(b""), Format::Rmp);
+        assert_eq!(Format::detect(&[0x80]), Format::Rmp);
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let map = BTreeMap::from([("a".to_string(), vec![1, 2]), ("b".to_string(), vec![])]);
+
+        for format in Format::ALL {
+            let decoded: BTreeMap<String, Vec<i32>> = format.decode(&format.encode(&map)?)?;
+
+            assert_eq!(decoded, map);
+            assert_eq!(Format::from_ext(format.ext()), Some(format));
+        }
+
+        Ok(())
+    }
+}
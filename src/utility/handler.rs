@@ -1,4 +1,8 @@
-use serenity::{all::OnlineStatus, gateway::ActivityData, prelude::EventHandler};
+use serenity::{
+    all::{Guild, OnlineStatus, UnavailableGuild},
+    gateway::{ActivityData, ConnectionStage, ShardStageUpdateEvent},
+    prelude::EventHandler,
+};
 
 use crate::prelude::*;
 
@@ -22,20 +26,6 @@ impl Handler {
         self.logger.error(s).ok();
     }
 
-    fn __create_commands() -> Vec<CreateCommand> {
-        vec![
-            apply::new(),
-            data::new(),
-            embed::new(),
-            help::new(),
-            offer::new(),
-            oracle::new(),
-            ping::new(),
-            poll::new(),
-            quote::new(),
-            role::new(),
-        ]
-    }
     async fn __update_presence(&self, ctx: &Context) -> Result<()> {
         let status = if IS_DEV {
             OnlineStatus::Idle
@@ -57,26 +47,24 @@ impl Handler {
         Ok(())
     }
     async fn __update_commands(&self, ctx: &Context) -> Result<()> {
-        let guild_id = dev_guild()?;
-        let cmds = Self::__create_commands();
-
-        let global = if IS_DEV {
-            ctx.http.get_global_application_commands().await?
+        let logger = self.logger.target(Registration::DIR);
+        let scopes = if IS_DEV {
+            dev_guilds()?.into_iter().map(Scope::Guild).collect()
         } else {
-            ctx.http.create_global_application_commands(&cmds).await?
-        }
-        .len();
-
-        self.info(format!("Global commands: {global}"));
+            vec![Scope::Global]
+        };
 
-        let guild = if IS_DEV {
-            guild_id.set_application_commands(ctx, cmds).await?
-        } else {
-            guild_id.get_application_commands(ctx).await?
+        // a failing scope should not keep the others from being updated
+        for scope in scopes {
+            match REGISTRY.sync(&ctx.http, scope).await {
+                Ok(changes) if changes.is_empty() => {
+                    logger.info(format!("Commands up to date: {scope}"))
+                }
+                Ok(changes) => logger.info(format!("Commands updated: {scope}, {changes}")),
+                Err(e) => logger.warn(format!("Commands not updated: {scope}, {e}")),
+            }
+            .ok();
         }
-        .len();
-
-        self.info(format!("Guild commands: {guild}"));
 
         Ok(())
     }
@@ -87,8 +75,11 @@ impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
         self.info(format!("Connected: {}", ready.user.tag()));
 
-        if let Some(n) = ready.shard.map(|s| s.total) {
-            self.info(format!("Shards: {n}"));
+        if let Some(shard) = ready.shard {
+            self.info(format!("Shards: {}", shard.total));
+
+            health().set_total(shard.total);
+            health().shard(shard.id, true);
         }
         if let Err(e) = self.__update_presence(&ctx).await {
             self.warn(e.to_string());
@@ -97,6 +88,44 @@ impl EventHandler for Handler {
             self.warn(e.to_string());
         }
     }
+    async fn shard_stage_update(&self, _: Context, event: ShardStageUpdateEvent) {
+        health().shard(event.shard_id, event.new == ConnectionStage::Connected);
+        self.logger
+            .target("shard")
+            .debug(format!(
+                "Shard {}: {:?} -> {:?}",
+                event.shard_id, event.old, event.new
+            ))
+            .ok();
+    }
+    async fn guild_create(&self, _: Context, guild: Guild, _: Option<bool>) {
+        let logger = self.logger.target(Departed::DIR);
+
+        match rejoin_guild(guild.id).await {
+            Ok(true) => logger.info(format!("Restored: Guild<{}>", guild.id)),
+            Ok(false) => logger.trace(format!("Available: Guild<{}>", guild.id)),
+            Err(e) => logger.warn(e.to_string()),
+        }
+        .ok();
+    }
+    async fn guild_delete(&self, _: Context, incomplete: UnavailableGuild, _: Option<Guild>) {
+        // unavailable guilds are outages rather than removals
+        if incomplete.unavailable {
+            return;
+        }
+
+        let logger = self.logger.target(Departed::DIR);
+
+        match depart_guild(incomplete.id).await {
+            Ok(deadline) => logger.info(format!(
+                "Departed: Guild<{}>, purging after {}",
+                incomplete.id,
+                deadline.with_timezone(&Local).format("%y-%m-%d %H:%M:%S")
+            )),
+            Err(e) => logger.warn(e.to_string()),
+        }
+        .ok();
+    }
     async fn interaction_create(&self, ctx: Context, mut int: Interaction) {
         let id = match &int {
             Interaction::Autocomplete(i) | Interaction::Command(i) => {
@@ -106,37 +135,92 @@ impl EventHandler for Handler {
             Interaction::Modal(i) => format!("{}.{}", i.data.custom_id, i.id),
             Interaction::Ping(i) => format!("{}.{}", i.token, i.id),
         };
-        let name = format!("{:?}<{id}>", int.kind());
+        let (target, guild, user) = match &int {
+            Interaction::Autocomplete(i) | Interaction::Command(i) => {
+                (i.data.name.clone(), i.guild_id, Some(i.user.id))
+            }
+            Interaction::Component(i) => (
+                CustomId::new(&i.data.custom_id).base,
+                i.guild_id,
+                Some(i.user.id),
+            ),
+            Interaction::Modal(i) => (
+                CustomId::new(&i.data.custom_id).base,
+                i.guild_id,
+                Some(i.user.id),
+            ),
+            Interaction::Ping(_) => ("ping".to_string(), None, None),
+        };
+        let mut fields = LogFields {
+            kind: Some(format!("{:?}", int.kind())),
+            command: Some(target.clone()),
+            interaction: Some(id.clone()),
+            guild,
+            user,
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
         let http = &ctx.http;
 
+        // held until the interaction is handled, so that shutdown waits for it
+        let task = shutdown().task();
+
+        // guild access rules only gate invoking a command, so the messages it has already sent keep
+        // working for everyone, such as poll votes, role buttons and application forms
+        let permit = match &int {
+            _ if task.is_none() => Err(Error::Forbidden(
+                "The bot is restarting, try again shortly",
+            )),
+            Interaction::Autocomplete(i) | Interaction::Command(i) => {
+                settings::permit(&target, i.guild_id, i.channel_id, i.member.as_deref())
+            }
+            _ => Ok(()),
+        };
+        let logger = self.logger.target(target);
+
         let result: Result<()> = match &mut int {
-            Interaction::Command(i) => match i.data.name.as_str() {
-                apply::NAME => apply::run_command(http, i).await,
-                data::NAME => data::run_command(http, i).await,
-                embed::NAME => embed::run_command(http, i).await,
-                help::NAME => help::run_command(http, i).await,
-                offer::NAME => offer::run_command(http, i).await,
-                oracle::NAME => oracle::run_command(http, i).await,
-                ping::NAME => ping::run_command(http, i).await,
-                poll::NAME => poll::run_command(http, i).await,
-                quote::NAME => quote::run_command(http, i).await,
-                role::NAME => role::run_command(http, i).await,
-                _ => Err(Error::InvalidValue(Value::Command, id)),
+            _ if permit.is_err() => permit,
+            Interaction::Autocomplete(i) => match REGISTRY.get(&i.data.name) {
+                Ok(c) => c.run_autocomplete(&ctx, i).await,
+                Err(e) => Err(e),
+            },
+            // a cooldown is only spent on a successful run, so failures can be retried at once
+            Interaction::Command(i) => match REGISTRY.get(&i.data.name) {
+                Ok(c) => {
+                    let cooldown = c.cooldown();
+
+                    match cooldowns().check(cooldown, i.guild_id, i.channel_id, i.user.id) {
+                        Ok(()) => c.run_command(http, i).await.inspect_err(|_| {
+                            cooldowns().refund(cooldown, i.guild_id, i.channel_id, i.user.id);
+                        }),
+                        Err(e) => Err(e),
+                    }
+                }
+                Err(e) => Err(e),
             },
             Interaction::Component(i) => match CustomId::try_from(i.data.custom_id.as_str()) {
-                Ok(c) => match c.base.as_str() {
-                    apply::NAME => apply::run_component(http, i).await,
-                    poll::NAME => poll::run_component(http, i).await,
-                    role::NAME => role::run_component(http, i).await,
-                    _ => Err(Error::InvalidValue(Value::Component, id)),
+                Ok(custom_id) => match REGISTRY.get(&custom_id.base) {
+                    Ok(c) => {
+                        let cooldown = c
+                            .component_cooldown()
+                            .filter(|_| c.uses_cooldown(&custom_id));
+                        let (guild, channel, user) = (i.guild_id, i.channel_id, i.user.id);
+
+                        match cooldowns().check(cooldown, guild, channel, user) {
+                            Ok(()) => c.run_component(http, i).await.inspect_err(|_| {
+                                cooldowns().refund(cooldown, guild, channel, user);
+                            }),
+                            Err(e) => Err(e),
+                        }
+                    }
+                    Err(_) => Err(Error::InvalidValue(Value::Component, id)),
                 },
                 Err(e) => Err(e),
             },
             Interaction::Modal(i) => match CustomId::try_from(i.data.custom_id.as_str()) {
-                Ok(c) => match c.base.as_str() {
-                    apply::NAME => apply::run_modal(http, i).await,
-                    poll::NAME => poll::run_modal(http, i).await,
-                    _ => Err(Error::InvalidValue(Value::Modal, id)),
+                Ok(c) => match REGISTRY.get(&c.base) {
+                    Ok(c) => c.run_modal(http, i).await,
+                    Err(_) => Err(Error::InvalidValue(Value::Modal, id)),
                 },
                 Err(e) => Err(e),
             },
@@ -146,24 +230,60 @@ impl EventHandler for Handler {
             )),
         };
 
+        let elapsed = start.elapsed();
+
+        fields.duration_ms = Some(elapsed.as_millis());
+        metrics().interaction(
+            &format!("{:?}", int.kind()),
+            fields.command.as_deref().unwrap_or_default(),
+            elapsed,
+            result.as_ref().err(),
+        );
+
         if let Err(error) = result {
-            self.error(format!("Failed: {name} - {error}"));
+            fields.error = Some(error.to_string());
+            // moderators only need to see failures on our end, not mistakes in a user's input
+            fields.mirror = !error.is_user_facing();
+
+            // internal failures are only described in the logs, under the code the user is shown
+            let description = if error.is_user_facing() {
+                format!("> {}", error.message())
+            } else {
+                let reference = Error::reference();
+
+                fields.reference = Some(reference.clone());
+
+                format!("> {}\n> Reference: `{reference}`", error.message())
+            };
+
+            // cooldowns and access rules are expected, so they should not be reported like failures
+            if matches!(error, Error::Cooldown(_) | Error::Forbidden(_)) {
+                logger.fields(fields.clone()).debug("Interaction refused")
+            } else {
+                logger.fields(fields.clone()).error("Interaction failed")
+            }
+            .ok();
 
             let embed = CreateEmbed::new()
                 .color(BOT_COLOR)
-                .description(format!("> {error}"))
+                .description(description)
                 .title("Encountered an error!");
-            let message = CreateInteractionResponseMessage::new()
-                .embed(embed)
-                .ephemeral(true);
-            let reply = CreateInteractionResponse::Message(message);
 
+            // errors cannot be shown in suggestions, so failures reply with an empty list; deferred
+            // interactions have their loading reply edited instead
             let result = match &int {
-                Interaction::Command(i) => i.create_response(ctx, reply).await.map_err(Error::from),
-                Interaction::Component(i) => {
-                    i.create_response(ctx, reply).await.map_err(Error::from)
+                Interaction::Autocomplete(i) => {
+                    let reply =
+                        CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new());
+
+                    i.create_response(ctx, reply)
+                        .counted("create_interaction_response")
+                        .await
+                        .map_err(Error::from)
                 }
-                Interaction::Modal(i) => i.create_response(ctx, reply).await.map_err(Error::from),
+                Interaction::Command(i) => i.reply(http, embed, true).await,
+                Interaction::Component(i) => i.reply(http, embed, true).await,
+                Interaction::Modal(i) => i.reply(http, embed, true).await,
                 _ => Err(Error::InvalidValue(
                     Value::Interaction,
                     format!("{:?}", int.kind()),
@@ -171,10 +291,14 @@ impl EventHandler for Handler {
             };
 
             if let Err(error) = result {
-                self.warn(format!("Silent error: {error}"));
+                fields.error = Some(error.to_string());
+                fields.mirror = true;
+                logger.fields(fields).warn("Error response failed").ok();
             }
         } else {
-            self.info(format!("Succeeded: {name}"));
+            logger.fields(fields).debug("Interaction succeeded").ok();
         }
+
+        deferrals().remove(int.id());
     }
 }
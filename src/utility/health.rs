@@ -0,0 +1,109 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+use crate::prelude::*;
+
+static HEALTH: OnceLock<Health> = OnceLock::new();
+
+// process state reported by the health and readiness endpoints
+#[derive(Debug)]
+pub struct Health {
+    started: Instant,
+    shards: Mutex<BTreeMap<ShardId, bool>>,
+    total: AtomicU32,
+    checked: Mutex<Option<Instant>>,
+}
+
+impl Health {
+    pub const KEY: &str = "READY_CHECK_INTERVALS";
+    pub const DEFAULT_INTERVALS: u32 = 3;
+    pub const CHECK_PERIOD: Duration = Duration::from_secs(10);
+
+    pub fn shard(&self, id: ShardId, connected: bool) {
+        if let Ok(mut shards) = self.shards.lock() {
+            shards.insert(id, connected);
+        }
+    }
+    pub fn set_total(&self, total: u32) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+    pub fn checked(&self) {
+        if let Ok(mut checked) = self.checked.lock() {
+            *checked = Some(Instant::now());
+        }
+    }
+
+    pub fn liveness(&self) -> serde_json::Value {
+        json!({
+            "status": "ok",
+            "uptime_secs": self.started.elapsed().as_secs(),
+        })
+    }
+
+    // the bot is ready once every shard is connected, polls are being checked and storage is writable
+    pub fn readiness(&self) -> Result<(bool, serde_json::Value)> {
+        let intervals = env_var(Self::KEY, Self::DEFAULT_INTERVALS)?;
+        let max = Self::CHECK_PERIOD * intervals.max(1);
+
+        let total = self.total.load(Ordering::Relaxed);
+        let connected = self
+            .shards
+            .lock()
+            .map_or(0, |s| s.values().filter(|c| **c).count());
+        let shards_ok = total > 0 && connected == total as usize;
+
+        let checked = self
+            .checked
+            .lock()
+            .ok()
+            .and_then(|c| *c)
+            .map(|c| c.elapsed());
+        let poll_ok = checked.is_some_and(|c| c <= max);
+
+        let storage = storage().probe();
+        let ready = shards_ok && poll_ok && storage.is_ok();
+
+        let details = json!({
+            "status": if ready { "ready" } else { "unready" },
+            "shards": {
+                "ok": shards_ok,
+                "connected": connected,
+                "total": total,
+            },
+            "poll_check": {
+                "ok": poll_ok,
+                "last_success_secs": checked.map(|c| c.as_secs()),
+                "max_secs": max.as_secs(),
+            },
+            "storage": {
+                "ok": storage.is_ok(),
+                "error": storage.err().map(|e| e.to_string()),
+            },
+        });
+
+        Ok((ready, details))
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            shards: Mutex::default(),
+            total: AtomicU32::default(),
+            checked: Mutex::default(),
+        }
+    }
+}
+
+pub fn health() -> &'static Health {
+    HEALTH.get_or_init(Health::default)
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+// guilds that removed the bot, mapped to the time their stored data gets purged
+#[repr(transparent)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Departed(pub BTreeMap<GuildId, DateTime<Utc>>);
+
+impl Departed {
+    pub const DIR: &str = "guild";
+    pub const KEY: &str = "PURGE_GRACE_HOURS";
+    pub const DEFAULT_HOURS: u32 = 168;
+
+    pub fn grace() -> Result<chrono::Duration> {
+        let hours = std::env::var(Self::KEY).map_or(Ok(Self::DEFAULT_HOURS), |raw| {
+            raw.parse()
+                .map_err(|_| Error::InvalidValue(Value::Other("Grace period"), raw))
+        })?;
+
+        Ok(chrono::Duration::hours(i64::from(hours)))
+    }
+
+    pub fn is_pending(&self, guild: GuildId) -> bool {
+        self.0.contains_key(&guild)
+    }
+}
+
+impl Versioned for Departed {}
+
+impl NewReq<()> for Departed {
+    fn new_req(_: ()) -> Req<Self> {
+        Req::new(Self::DIR, ".dat")
+    }
+}
+
+impl AsReq<()> for Departed {
+    fn as_req(&self, _: ()) -> Req<Self> {
+        Self::new_req(())
+    }
+}
+
+// schedules the guild's data for deletion, keeping the earlier deadline if one already exists
+pub async fn depart_guild(guild: GuildId) -> Result<DateTime<Utc>> {
+    let grace = Departed::grace()?;
+    let _lock = Departed::new_req(()).lock().await?;
+    let mut departed = Departed::read(()).unwrap_or_default();
+    let deadline = *departed
+        .0
+        .entry(guild)
+        .or_insert_with(|| Utc::now() + grace);
+
+    departed.write(())?;
+
+    Ok(deadline)
+}
+pub async fn rejoin_guild(guild: GuildId) -> Result<bool> {
+    let _lock = Departed::new_req(()).lock().await?;
+    let mut departed = match Departed::read(()) {
+        Ok(departed) => departed,
+        Err(error) if error.is_not_found() => return Ok(false),
+        Err(error) => return Err(error),
+    };
+
+    if departed.0.remove(&guild).is_none() {
+        return Ok(false);
+    }
+
+    departed.write(())?;
+
+    Ok(true)
+}
+pub async fn purge_departed() -> Result<Vec<GuildId>> {
+    let _lock = Departed::new_req(()).lock().await?;
+    let mut departed = match Departed::read(()) {
+        Ok(departed) => departed,
+        Err(error) if error.is_not_found() => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+
+    let now = Utc::now();
+    let due: Vec<_> = departed
+        .0
+        .iter()
+        .filter(|(_, deadline)| **deadline <= now)
+        .map(|(guild, _)| *guild)
+        .collect();
+
+    for guild in &due {
+        crate::command::purge(*guild).await?;
+        departed.0.remove(guild);
+        departed.write(())?;
+    }
+
+    Ok(due)
+}
@@ -1,15 +1,23 @@
 use std::{
-    fs::{create_dir_all, File},
+    collections::BTreeMap,
+    fs::{create_dir_all, read_dir, remove_file, File},
     io::{stdout, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
 };
 
+use chrono::NaiveDate;
 use colored::{Color, Colorize};
+use flate2::{write::GzEncoder, Compression};
 
 use crate::prelude::*;
 
-#[derive(Clone, Copy, Debug)]
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
+    Trace,
+    Debug,
     Info,
     Warn,
     Error,
@@ -18,6 +26,8 @@ pub enum LogLevel {
 impl LogLevel {
     pub const fn color(self) -> Color {
         match self {
+            Self::Trace => Color::BrightBlack,
+            Self::Debug => Color::Magenta,
             Self::Info => Color::BrightBlue,
             Self::Warn => Color::Yellow,
             Self::Error => Color::BrightRed,
@@ -31,86 +41,576 @@ impl std::fmt::Display for LogLevel {
     }
 }
 
+impl TryFrom<&str> for LogLevel {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(Error::InvalidValue(
+                Value::Other("Log level"),
+                value.to_string(),
+            )),
+        }
+    }
+}
+
+// a minimum level, optionally overridden per target, written as `info,poll=debug,apply=warn`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFilter {
+    pub level: LogLevel,
+    pub targets: BTreeMap<String, LogLevel>,
+}
+
+impl LogFilter {
+    pub const KEY: &str = "LOG_LEVEL";
+
+    pub fn from_env() -> Result<Self> {
+        arg("log-level")
+            .or_else(|| std::env::var(Self::KEY).ok())
+            .map_or_else(|| Ok(Self::default()), |s| Self::try_from(s.as_str()))
+    }
+
+    pub fn level(&self, target: Option<&str>) -> LogLevel {
+        target
+            .and_then(|t| self.targets.get(t))
+            .copied()
+            .unwrap_or(self.level)
+    }
+    pub fn allows(&self, level: LogLevel, target: Option<&str>) -> bool {
+        level >= self.level(target)
+    }
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            targets: BTreeMap::new(),
+        }
+    }
+}
+
+impl TryFrom<&str> for LogFilter {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        let mut filter = Self::default();
+
+        for part in value.split(',').filter(|s| !s.trim().is_empty()) {
+            if let Some((target, level)) = part.split_once('=') {
+                filter
+                    .targets
+                    .insert(target.trim().to_string(), LogLevel::try_from(level)?);
+            } else {
+                filter.level = LogLevel::try_from(part)?;
+            }
+        }
+
+        Ok(filter)
+    }
+}
+
+// where file entries are written: free text lines, or one JSON object per line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogSink {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LogSink {
+    pub const KEY: &str = "LOG_FORMAT";
+    pub const ALL: [Self; 2] = [Self::Text, Self::Json];
+
+    pub fn from_env() -> Result<Self> {
+        std::env::var(Self::KEY).map_or_else(|_| Ok(Self::default()), Self::try_from)
+    }
+
+    pub const fn ext(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Json => "jsonl",
+        }
+    }
+}
+
+impl TryFrom<String> for LogSink {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "text" | "txt" => Ok(Self::Text),
+            "json" | "jsonl" => Ok(Self::Json),
+            _ => Err(Error::InvalidValue(Value::Other("Log format"), value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LogFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interaction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild: Option<GuildId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    // whether a warning or error should also be posted to the guild's log channel
+    #[serde(skip)]
+    pub mirror: bool,
+}
+
+impl LogFields {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl std::fmt::Display for LogFields {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Ok(serde_json::Value::Object(map)) = serde_json::to_value(self) else {
+			return Ok(());
+		};
+
+        let pairs: Vec<_> = map
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(s) if !s.contains(char::is_whitespace) => {
+                    format!("{key}={s}")
+                }
+                value => format!("{key}={value}"),
+            })
+            .collect();
+
+        write!(f, "{}", pairs.join(" "))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Log<'log> {
     pub level: LogLevel,
-    pub time: String,
+    pub time: DateTime<Local>,
+    pub target: Option<&'log str>,
     pub text: &'log str,
+    pub fields: Option<&'log LogFields>,
 }
 
 impl<'log> Log<'log> {
-    pub fn new(level: LogLevel, text: &'log str) -> Self {
-        let now = Local::now();
-        let time = now.format("[%y-%m-%d %H:%M:%S.%3f]").to_string();
+    pub fn new(level: LogLevel, target: Option<&'log str>, text: &'log str) -> Self {
+        Self {
+            level,
+            time: Local::now(),
+            target,
+            text,
+            fields: None,
+        }
+    }
+    pub const fn with_fields(mut self, fields: &'log LogFields) -> Self {
+        self.fields = Some(fields);
+        self
+    }
 
-        Self { level, time, text }
+    fn __time(&self) -> String {
+        self.time.format("[%y-%m-%d %H:%M:%S.%3f]").to_string()
+    }
+    fn __text(&self) -> String {
+        let text = self.text.trim();
+
+        match self.fields.filter(|f| !f.is_empty()) {
+            Some(fields) => format!("{text} {fields}"),
+            None => text.to_string(),
+        }
     }
 
     pub fn to_colored_string(&self) -> String {
-        let time = self.time.dimmed();
+        let time = self.__time().dimmed();
         let kind = self.level.to_string().color(self.level.color());
-        let text = self.text.trim().white();
+        let text = self.__text().white();
+
+        if let Some(target) = self.target {
+            format!("{time} {kind} {} {text}\n", format!("{target}:").dimmed())
+        } else {
+            format!("{time} {kind} {text}\n")
+        }
+    }
+    pub fn to_json_string(&self) -> Result<String> {
+        let mut map = serde_json::Map::new();
+        let level = format!("{:?}", self.level).to_lowercase();
+
+        map.insert("time".to_string(), self.time.to_rfc3339().into());
+        map.insert("level".to_string(), level.into());
+
+        if let Some(target) = self.target {
+            map.insert("target".to_string(), target.into());
+        }
+
+        map.insert("message".to_string(), self.text.trim().into());
+
+        if let Some(serde_json::Value::Object(fields)) =
+            self.fields.map(serde_json::to_value).transpose()?
+        {
+            map.extend(fields);
+        }
 
-        format!("{time} {kind} {text}\n")
+        Ok(format!("{}\n", serde_json::Value::Object(map)))
+    }
+    pub fn to_sink_string(&self, sink: LogSink) -> Result<String> {
+        match sink {
+            LogSink::Text => Ok(self.to_string()),
+            LogSink::Json => self.to_json_string(),
+        }
     }
 }
 
 impl std::fmt::Display for Log<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = self.__time();
         let kind = self.level.to_string();
-        let text = self.text.trim();
+        let text = self.__text();
 
-        writeln!(f, "{} {kind} {text}", self.time)
+        if let Some(target) = self.target {
+            writeln!(f, "{time} {kind} {target}: {text}")
+        } else {
+            writeln!(f, "{time} {kind} {text}")
+        }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogRotation {
+    // size in bytes after which a new file is started, or zero to only rotate daily
+    pub max_bytes: u64,
+    // number of rotated files to keep, or zero to keep all of them
+    pub retain: usize,
+    pub compress: bool,
+}
+
+impl LogRotation {
+    pub const KEY_MAX_BYTES: &str = "LOG_MAX_BYTES";
+    pub const KEY_RETAIN: &str = "LOG_RETAIN";
+    pub const KEY_COMPRESS: &str = "LOG_COMPRESS";
+
+    pub fn from_env() -> Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            max_bytes: env_var(Self::KEY_MAX_BYTES, default.max_bytes)?,
+            retain: env_var(Self::KEY_RETAIN, default.retain)?,
+            compress: env_var(Self::KEY_COMPRESS, default.compress)?,
+        })
+    }
+
+    // returns every finished log file, oldest first
+    pub fn files(current: Option<&Path>) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+
+        for entry in read_dir(Logger::DIR)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            let name = name.trim_end_matches(".gz");
+
+            if Some(path.as_path()) != current
+                && LogSink::ALL.iter().any(|s| name.ends_with(s.ext()))
+            {
+                files.push(path);
+            }
+        }
+
+        // names embed their creation time, so lexical order is chronological
+        files.sort();
+
+        Ok(files)
+    }
+
+    pub fn prune(self, current: Option<&Path>) -> Result<Vec<PathBuf>> {
+        if self.retain == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut files = Self::files(current)?;
+        let count = files.len().saturating_sub(self.retain);
+        let pruned: Vec<_> = files.drain(..count).collect();
+
+        for path in &pruned {
+            remove_file(path)?;
+        }
+
+        Ok(pruned)
+    }
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            retain: 14,
+            compress: true,
+        }
+    }
+}
+
+// the file is only created once the first entry is written, so its settings can still change
+#[derive(Debug, Default)]
+struct LogFile {
+    path: Option<PathBuf>,
+    day: Option<NaiveDate>,
+    size: u64,
+    rotation: LogRotation,
+    sink: LogSink,
+}
+
+impl LogFile {
+    fn __open(&mut self) -> Result<PathBuf> {
+        let now = Local::now();
+        let name = now.format("%y%m%d_%H%M%S_%6f").to_string();
+        let dir = PathBuf::from(Logger::DIR);
+
+        create_dir_all(&dir)?;
+
+        let path = dir.join(name).with_extension(self.sink.ext());
+
+        File::create(&path)?;
+
+        self.day = Some(now.date_naive());
+        self.size = 0;
+
+        Ok(path)
+    }
+
+    fn should_rotate(&self, len: u64, day: NaiveDate) -> bool {
+        let max = self.rotation.max_bytes;
+
+        self.path.is_some()
+            && (Some(day) != self.day || (max > 0 && self.size > 0 && self.size + len > max))
+    }
+
+    fn __compress(path: &Path) -> Result<()> {
+        let mut target = path.as_os_str().to_owned();
+
+        target.push(".gz");
+
+        let mut encoder = GzEncoder::new(File::create(target)?, Compression::default());
+
+        std::io::copy(&mut File::open(path)?, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+        remove_file(path)?;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        // the new file is opened first so that a failure leaves the current one in place
+        let next = self.__open()?;
+        let last = self.path.replace(next);
+
+        if let (Some(last), true) = (last, self.rotation.compress) {
+            Self::__compress(&last)?;
+        }
+
+        self.rotation.prune(self.path.as_deref())?;
+
+        Ok(())
+    }
+
+    fn sync(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => File::open(path)?.sync_all().map_err(Error::from),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => {
+                let path = self.__open()?;
+
+                self.path.insert(path).clone()
+            }
+        };
+        let mut file = File::options().append(true).open(path)?;
+
+        file.write_all(text.as_bytes())?;
+        self.size += text.len() as u64;
+
+        Ok(())
+    }
+}
+
+pub fn init_logger(logger: Logger) -> Result<()> {
+    LOGGER
+        .set(logger)
+        .map_err(|_| Error::Other("The logger has already been initialized"))
+}
+// used where no logger is passed in; entries are dropped until one has been initialized
+pub fn logger() -> Option<&'static Logger> {
+    LOGGER.get()
+}
+
 #[derive(Clone, Debug)]
 pub struct Logger {
-    path: PathBuf,
-    store: bool,
+    file: Option<Arc<Mutex<LogFile>>>,
     enable: bool,
+    filter: Arc<LogFilter>,
+    target: Option<String>,
+    fields: Option<Arc<LogFields>>,
+    mirror: Option<Mirror>,
 }
 
 impl Logger {
     pub const DIR: &str = "logs";
-    pub const EXT: &str = "txt";
 
     pub fn new(store: bool, enable: bool) -> Result<Self> {
-        let now = Local::now();
-        let name = now.format("%y%m%d_%H%M%S_%6f").to_string();
-        let dir = PathBuf::from(Self::DIR);
+        let file = if store {
+            create_dir_all(Self::DIR)?;
 
-        create_dir_all(&dir)?;
+            Some(Arc::new(Mutex::new(LogFile::default())))
+        } else {
+            None
+        };
 
-        let path = dir.join(name).with_extension(Self::EXT);
+        Ok(Self {
+            file,
+            enable,
+            filter: Arc::new(LogFilter::default()),
+            target: None,
+            fields: None,
+            mirror: None,
+        })
+    }
 
-        if store {
-            File::create(&path)?;
+    pub fn with_filter(mut self, filter: LogFilter) -> Self {
+        self.filter = Arc::new(filter);
+        self
+    }
+    pub fn with_rotation(self, rotation: LogRotation) -> Self {
+        if let Some(Ok(mut file)) = self.file.as_ref().map(|f| f.lock()) {
+            file.rotation = rotation;
         }
 
-        Ok(Self {
-            path,
-            store,
-            enable,
+        self
+    }
+    pub fn with_mirror(mut self, mirror: Mirror) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+    pub fn with_sink(self, sink: LogSink) -> Self {
+        if let Some(Ok(mut file)) = self.file.as_ref().map(|f| f.lock()) {
+            file.sink = sink;
+        }
+
+        self
+    }
+    // returns a logger whose entries are labelled and filtered under the given target
+    pub fn target(&self, target: impl Into<String>) -> Self {
+        let mut logger = self.clone();
+
+        logger.target = Some(target.into());
+        logger
+    }
+    // returns a logger whose entries carry the given structured fields
+    pub fn fields(&self, fields: LogFields) -> Self {
+        let mut logger = self.clone();
+
+        logger.fields = Some(Arc::new(fields));
+        logger
+    }
+    pub fn guild(&self, guild: GuildId) -> Self {
+        self.fields(LogFields {
+            guild: Some(guild),
+            mirror: true,
+            ..Default::default()
         })
     }
 
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        self.enable && self.filter.allows(level, self.target.as_deref())
+    }
+
     fn __log(&self, level: LogLevel, text: &str) -> Result<()> {
-        let log = Log::new(level, text);
+        if !self.enabled(level) {
+            return Ok(());
+        }
 
-        if self.enable {
-            stdout().write_all(log.to_colored_string().as_bytes())?;
+        let mut log = Log::new(level, self.target.as_deref(), text);
 
-            if self.store {
-                let mut file = File::options().append(true).open(&self.path)?;
+        if let Some(fields) = &self.fields {
+            log = log.with_fields(fields);
+        }
+
+        stdout().write_all(log.to_colored_string().as_bytes())?;
 
-                file.write_all(log.to_string().as_bytes())?;
+        if let (Some(mirror), Some(fields)) = (&self.mirror, log.fields) {
+            if let (true, Some(guild)) = (fields.mirror, fields.guild) {
+                mirror.push(guild, &log);
             }
         }
 
-        Ok(())
+        let Some(file) = &self.file else {
+			return Ok(());
+		};
+        let Ok(mut file) = file.lock() else {
+			return Err(Error::Other("The log file has been poisoned"));
+		};
+
+        let text = log.to_sink_string(file.sink)?;
+        let day = log.time.date_naive();
+
+        if file.should_rotate(text.len() as u64, day) {
+            if let Err(error) = file.rotate() {
+                // keep the current file and wait for the next threshold before trying again
+                file.day = Some(day);
+                file.size = 0;
+
+                let text = format!("Log rotation failed: {error}");
+                let log = Log::new(LogLevel::Warn, self.target.as_deref(), &text);
+                let sink = file.sink;
+
+                stdout().write_all(log.to_colored_string().as_bytes())?;
+                file.write(&log.to_sink_string(sink)?)?;
+            }
+        }
+
+        file.write(&text)
     }
 
+    // writes are unbuffered, so this only needs to make sure that they have reached the disk
+    pub fn flush(&self) -> Result<()> {
+        stdout().flush()?;
+
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let Ok(file) = file.lock() else {
+            return Err(Error::Other("The log file has been poisoned"));
+        };
+
+        file.sync()
+    }
+
+    pub fn trace(&self, s: impl Into<String>) -> Result<()> {
+        self.__log(LogLevel::Trace, &s.into())
+    }
+    pub fn debug(&self, s: impl Into<String>) -> Result<()> {
+        self.__log(LogLevel::Debug, &s.into())
+    }
     pub fn info(&self, s: impl Into<String>) -> Result<()> {
         self.__log(LogLevel::Info, &s.into())
     }
@@ -121,3 +621,47 @@ impl Logger {
         self.__log(LogLevel::Error, &s.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_level_and_targets() -> Result<()> {
+        let filter = LogFilter::try_from("warn, poll=debug ,apply=TRACE")?;
+
+        assert_eq!(filter.level, LogLevel::Warn);
+        assert_eq!(filter.level(Some("poll")), LogLevel::Debug);
+        assert_eq!(filter.level(Some("apply")), LogLevel::Trace);
+        assert_eq!(filter.level(Some("role")), LogLevel::Warn);
+        assert_eq!(filter.level(None), LogLevel::Warn);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_empty_uses_default() -> Result<()> {
+        assert_eq!(LogFilter::try_from("")?, LogFilter::default());
+        assert_eq!(LogFilter::try_from(" , ")?, LogFilter::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_rejects_unknown_level() {
+        assert!(LogFilter::try_from("verbose").is_err());
+        assert!(LogFilter::try_from("info,poll=loud").is_err());
+    }
+
+    #[test]
+    fn allows_by_target() -> Result<()> {
+        let filter = LogFilter::try_from("info,poll=error")?;
+
+        assert!(filter.allows(LogLevel::Info, None));
+        assert!(!filter.allows(LogLevel::Debug, None));
+        assert!(!filter.allows(LogLevel::Warn, Some("poll")));
+        assert!(filter.allows(LogLevel::Error, Some("poll")));
+
+        Ok(())
+    }
+}
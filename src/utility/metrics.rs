@@ -0,0 +1,238 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use crate::prelude::*;
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    pub const BUCKETS: [f64; 11] = [
+        0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0,
+    ];
+
+    pub fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+
+        for (count, bound) in self.counts.iter_mut().zip(Self::BUCKETS) {
+            if secs <= bound {
+                *count += 1;
+            }
+        }
+
+        self.count += 1;
+        self.sum += secs;
+    }
+
+    fn __write(&self, out: &mut String, name: &str, labels: &str) -> std::fmt::Result {
+        let sep = if labels.is_empty() { "" } else { "," };
+
+        for (count, bound) in self.counts.iter().zip(Self::BUCKETS) {
+            writeln!(out, "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {count}")?;
+        }
+
+        writeln!(
+            out,
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+            self.count
+        )?;
+        writeln!(out, "{name}_sum{{{labels}}} {}", self.sum)?;
+        writeln!(out, "{name}_count{{{labels}}} {}", self.count)
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; Self::BUCKETS.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+}
+
+// process-wide counters, rendered in the prometheus text format
+#[derive(Debug, Default)]
+pub struct Metrics {
+    interactions: Mutex<BTreeMap<(String, String), u64>>,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+    latency: Mutex<BTreeMap<String, Histogram>>,
+    storage: Mutex<BTreeMap<&'static str, Histogram>>,
+    requests: Mutex<BTreeMap<&'static str, u64>>,
+    ratelimited: AtomicU64,
+}
+
+impl Metrics {
+    pub fn interaction(&self, kind: &str, name: &str, duration: Duration, error: Option<&Error>) {
+        if let Ok(mut map) = self.interactions.lock() {
+            *map.entry((kind.to_string(), name.to_string())).or_default() += 1;
+        }
+        if let Ok(mut map) = self.latency.lock() {
+            map.entry(kind.to_string()).or_default().observe(duration);
+        }
+        if let Some(error) = error {
+            self.error(error);
+        }
+    }
+    pub fn error(&self, error: &Error) {
+        if let Ok(mut map) = self.errors.lock() {
+            *map.entry(error.variant()).or_default() += 1;
+        }
+    }
+    pub fn storage(&self, op: &'static str, duration: Duration) {
+        if let Ok(mut map) = self.storage.lock() {
+            map.entry(op).or_default().observe(duration);
+        }
+    }
+    pub fn request(&self, route: &'static str) {
+        if let Ok(mut map) = self.requests.lock() {
+            *map.entry(route).or_default() += 1;
+        }
+    }
+    pub fn ratelimited(&self) {
+        self.ratelimited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn __label(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    fn __header(out: &mut String, name: &str, kind: &str, help: &str) -> std::fmt::Result {
+        writeln!(out, "# HELP {name} {help}")?;
+        writeln!(out, "# TYPE {name} {kind}")
+    }
+
+    pub fn render(&self) -> Result<String> {
+        let mut out = String::new();
+
+        self.__render(&mut out)
+            .map_err(|_| Error::Other("Failed to render metrics"))?;
+
+        Ok(out)
+    }
+    fn __render(&self, out: &mut String) -> std::fmt::Result {
+        const INTERACTIONS: &str = "mrm_interactions_total";
+        const ERRORS: &str = "mrm_errors_total";
+        const LATENCY: &str = "mrm_interaction_duration_seconds";
+        const STORAGE: &str = "mrm_storage_duration_seconds";
+        const REQUESTS: &str = "mrm_discord_http_requests_total";
+        const RATELIMITED: &str = "mrm_discord_http_ratelimited_total";
+        const POLLS: &str = "mrm_active_polls";
+
+        Self::__header(out, INTERACTIONS, "counter", "Handled interactions.")?;
+
+        if let Ok(map) = self.interactions.lock() {
+            for ((kind, name), count) in map.iter() {
+                let (kind, name) = (Self::__label(kind), Self::__label(name));
+
+                writeln!(
+                    out,
+                    "{INTERACTIONS}{{kind=\"{kind}\",name=\"{name}\"}} {count}"
+                )?;
+            }
+        }
+
+        Self::__header(out, ERRORS, "counter", "Errors by variant.")?;
+
+        if let Ok(map) = self.errors.lock() {
+            for (variant, count) in map.iter() {
+                writeln!(out, "{ERRORS}{{variant=\"{variant}\"}} {count}")?;
+            }
+        }
+
+        Self::__header(out, LATENCY, "histogram", "Interaction handling time.")?;
+
+        if let Ok(map) = self.latency.lock() {
+            for (kind, histogram) in map.iter() {
+                histogram.__write(out, LATENCY, &format!("kind=\"{}\"", Self::__label(kind)))?;
+            }
+        }
+
+        Self::__header(out, STORAGE, "histogram", "Record read and write time.")?;
+
+        if let Ok(map) = self.storage.lock() {
+            for (op, histogram) in map.iter() {
+                histogram.__write(out, STORAGE, &format!("op=\"{op}\""))?;
+            }
+        }
+
+        Self::__header(out, REQUESTS, "counter", "Discord HTTP API calls by route.")?;
+
+        if let Ok(map) = self.requests.lock() {
+            for (route, count) in map.iter() {
+                writeln!(out, "{REQUESTS}{{route=\"{route}\"}} {count}")?;
+            }
+        }
+
+        Self::__header(
+            out,
+            RATELIMITED,
+            "counter",
+            "Discord HTTP API calls delayed by a rate limit.",
+        )?;
+        writeln!(
+            out,
+            "{RATELIMITED} {}",
+            self.ratelimited.load(Ordering::Relaxed)
+        )?;
+
+        // read from storage on each scrape, so the gauge never drifts from the record; the record
+        // only exists once a poll has been opened
+        let polls = match poll::form::Active::read(()) {
+            Ok(active) => Some(active.0.len()),
+            Err(error) if error.is_not_found() => Some(0),
+            Err(_) => None,
+        };
+
+        if let Some(polls) = polls {
+            Self::__header(out, POLLS, "gauge", "Polls that are currently open.")?;
+            writeln!(out, "{POLLS} {polls}")?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::default)
+}
+
+// tags a discord http call so it is counted under its route; serenity has no hook that sees every
+// request, so each call site marks its own
+pub trait Counted: Future + Sized {
+    fn counted(self, route: &'static str) -> Self {
+        metrics().request(route);
+        self
+    }
+}
+
+impl<F: Future> Counted for F {}
+
+// builds an http client whose rate limit waits are counted; the ratelimiter callback only fires
+// when a request has to wait, so plain requests are counted through `Counted`
+pub fn new_http(token: &str) -> Http {
+    let mut http = Http::new(token);
+
+    if let Some(ratelimiter) = http.ratelimiter.as_mut() {
+        ratelimiter.set_ratelimit_callback(Box::new(|_| metrics().ratelimited()));
+    }
+
+    http
+}
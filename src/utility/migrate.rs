@@ -0,0 +1,223 @@
+use rmpv::Value as RawValue;
+
+use crate::prelude::*;
+
+pub type Migration = fn(RawValue) -> Result<RawValue>;
+
+// each migration upgrades a record by one version, so a type's version is its migration count
+pub trait Versioned: Sized + Serialize + for<'de> Deserialize<'de> {
+    const MIGRATIONS: &'static [Migration] = &[];
+
+    fn version() -> u32 {
+        u32::try_from(Self::MIGRATIONS.len()).unwrap_or(u32::MAX)
+    }
+
+    fn migrate(version: u32, mut data: RawValue) -> Result<RawValue> {
+        let Some(steps) = Self::MIGRATIONS.get(version as usize..) else {
+            return Err(Error::InvalidValue(
+                Value::Other("Version"),
+                version.to_string(),
+            ));
+        };
+
+        for step in steps {
+            data = step(data)?;
+        }
+
+        Ok(data)
+    }
+}
+
+// upgrades an unversioned, positionally encoded record into the named layout of `T`;
+// once `T` changes shape, freeze its old layout in a separate type and migrate through that
+pub fn legacy<T>(data: RawValue) -> Result<RawValue>
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    let value: T = from_raw(&data)?;
+    let raw = rmp_serde::to_vec_named(&value)?;
+
+    Ok(rmp_serde::from_slice(&raw)?)
+}
+
+pub fn from_raw<T>(data: &RawValue) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let raw = rmp_serde::to_vec(data)?;
+
+    Ok(rmp_serde::from_slice(&raw)?)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Envelope<'e, T> {
+    pub version: u32,
+    pub data: &'e T,
+}
+
+impl<'e, T: Versioned> Envelope<'e, T> {
+    pub const KEY_VERSION: &'static str = "version";
+    pub const KEY_DATA: &'static str = "data";
+
+    pub fn new(data: &'e T) -> Self {
+        let version = T::version();
+
+        Self { version, data }
+    }
+
+    pub fn seal(&self) -> Result<Vec<u8>> {
+        self.seal_as(storage_format())
+    }
+    pub fn seal_as(&self, format: Format) -> Result<Vec<u8>> {
+        format.encode(self)
+    }
+    // records written before envelopes existed are treated as version zero
+    pub fn open(raw: &[u8]) -> Result<(u32, RawValue)> {
+        let value: RawValue = Format::detect(raw).decode(raw)?;
+
+        let RawValue::Map(entries) = value else {
+            return Ok((0, value));
+        };
+
+        let mut version = None;
+        let mut data = None;
+
+        for (key, value) in &entries {
+            match key.as_str() {
+                Some(Self::KEY_VERSION) => version = value.as_u64(),
+                Some(Self::KEY_DATA) => data = Some(value.clone()),
+                _ => {}
+            }
+        }
+
+        match (version.map(u32::try_from), data) {
+            (Some(Ok(version)), Some(data)) => Ok((version, data)),
+            _ => Ok((0, RawValue::Map(entries))),
+        }
+    }
+    pub fn decode(raw: &[u8]) -> Result<T> {
+        let (version, data) = Self::open(raw)?;
+
+        from_raw(&T::migrate(version, data)?)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub checked: usize,
+    pub migrated: Vec<String>,
+    pub failed: Vec<(String, Error)>,
+}
+
+impl MigrationReport {
+    pub fn record<T>(&mut self, req: &Req<T>)
+    where
+        T: Send + Sync + Versioned,
+    {
+        match req.migrate() {
+            Ok(changed) => {
+                self.checked += 1;
+
+                if changed {
+                    self.migrated.push(req.id());
+                }
+            }
+            Err(error) if error.is_not_found() => {}
+            Err(error) => {
+                self.checked += 1;
+                self.failed.push((req.id(), error));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the layout of `Counter` before it gained a label
+    #[derive(Debug, Serialize, Deserialize)]
+    struct CounterV1 {
+        count: u32,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct Counter {
+        count: u32,
+        label: String,
+    }
+
+    fn __add_label(data: RawValue) -> Result<RawValue> {
+        let CounterV1 { count } = from_raw(&data)?;
+        let label = "none".to_string();
+        let raw = rmp_serde::to_vec_named(&Counter { count, label })?;
+
+        Ok(rmp_serde::from_slice(&raw)?)
+    }
+
+    impl Versioned for Counter {
+        const MIGRATIONS: &'static [Migration] = &[legacy::<CounterV1>, __add_label];
+    }
+
+    #[test]
+    fn decode_legacy_record() -> Result<()> {
+        let raw = rmp_serde::to_vec(&CounterV1 { count: 3 })?;
+        let counter = Envelope::<Counter>::decode(&raw)?;
+
+        assert_eq!(Envelope::<Counter>::open(&raw)?.0, 0);
+        assert_eq!(counter.count, 3);
+        assert_eq!(counter.label, "none");
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_older_version() -> Result<()> {
+        let old = CounterV1 { count: 5 };
+        let envelope = Envelope {
+            version: 1,
+            data: &old,
+        };
+        let raw = Format::Rmp.encode(&envelope)?;
+        let counter = Envelope::<Counter>::decode(&raw)?;
+
+        assert_eq!(counter.count, 5);
+        assert_eq!(counter.label, "none");
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip_current_version() -> Result<()> {
+        let counter = Counter {
+            count: 7,
+            label: "seven".to_string(),
+        };
+
+        for format in [Format::Rmp, Format::Json] {
+            let raw = Envelope::new(&counter).seal_as(format)?;
+
+            assert_eq!(Envelope::<Counter>::open(&raw)?.0, Counter::version());
+            assert_eq!(Envelope::<Counter>::decode(&raw)?, counter);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_newer_version() -> Result<()> {
+        let counter = Counter {
+            count: 1,
+            label: String::new(),
+        };
+        let envelope = Envelope {
+            version: Counter::version() + 1,
+            data: &counter,
+        };
+        let raw = Format::Rmp.encode(&envelope)?;
+
+        assert!(Envelope::<Counter>::decode(&raw).is_err());
+
+        Ok(())
+    }
+}
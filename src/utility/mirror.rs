@@ -0,0 +1,182 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use serenity::all::Timestamp;
+
+use crate::{command::settings::Settings, prelude::*};
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    level: LogLevel,
+    target: Option<String>,
+    text: String,
+    error: Option<String>,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{:?}`", self.level)?;
+
+        if let Some(target) = &self.target {
+            write!(f, " **{target}:**")?;
+        }
+
+        write!(f, " {}", self.text.trim())?;
+
+        if let Some(error) = &self.error {
+            write!(f, " - {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // entries waiting for the next flush, with the number of times each was logged
+    pending: BTreeMap<GuildId, BTreeMap<Entry, usize>>,
+    // when each entry was last posted
+    posted: BTreeMap<(GuildId, Entry), i64>,
+    // entries dropped as repeats or overflow since the guild's last post
+    suppressed: BTreeMap<GuildId, usize>,
+}
+
+// collects warnings and errors tied to a guild so they can be posted to its log channel
+#[derive(Clone, Debug, Default)]
+pub struct Mirror(Arc<Mutex<State>>);
+
+impl Mirror {
+    pub const KEY: &str = "LOG_MIRROR_SECS";
+    pub const DEFAULT_SECS: u64 = 60;
+    // the same entry is posted at most once per window, and repeats within it are dropped
+    pub const WINDOW_MS: i64 = 15 * 60 * 1000;
+    pub const MAX_PENDING: usize = 50;
+    pub const MAX_LINES: usize = 10;
+
+    pub fn period() -> Result<std::time::Duration> {
+        let secs = env_var(Self::KEY, Self::DEFAULT_SECS)?;
+
+        Ok(std::time::Duration::from_secs(secs.max(1)))
+    }
+
+    pub fn push(&self, guild: GuildId, log: &Log) {
+        if log.level < LogLevel::Warn {
+            return;
+        }
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+
+        let entry = Entry {
+            level: log.level,
+            target: log.target.map(ToString::to_string),
+            text: log.text.to_string(),
+            error: log.fields.and_then(|f| f.error.clone()),
+        };
+        let pending = state.pending.entry(guild).or_default();
+
+        if let Some(count) = pending.get_mut(&entry) {
+            *count += 1;
+        } else if pending.len() < Self::MAX_PENDING {
+            pending.insert(entry, 1);
+        } else {
+            *state.suppressed.entry(guild).or_default() += 1;
+        }
+    }
+
+    // drains the pending entries, dropping any that were already posted within the window; the
+    // dropped count is carried until the guild next has something to post
+    fn __take(&self, now: i64) -> BTreeMap<GuildId, (Vec<(Entry, usize)>, usize)> {
+        let Ok(mut state) = self.0.lock() else {
+            return BTreeMap::new();
+        };
+        let pending = std::mem::take(&mut state.pending);
+        let mut batches = BTreeMap::new();
+
+        state.posted.retain(|_, time| now - *time < Self::WINDOW_MS);
+
+        for (guild, entries) in pending {
+            let mut batch = vec![];
+
+            for (entry, count) in entries {
+                let key = (guild, entry);
+
+                if state.posted.contains_key(&key) {
+                    *state.suppressed.entry(guild).or_default() += count;
+                } else {
+                    state.posted.insert(key.clone(), now);
+                    batch.push((key.1, count));
+                }
+            }
+
+            if !batch.is_empty() {
+                let suppressed = state.suppressed.remove(&guild).unwrap_or_default();
+
+                batches.insert(guild, (batch, suppressed));
+            }
+        }
+
+        batches
+    }
+
+    fn __embed(entries: &[(Entry, usize)], suppressed: usize) -> CreateEmbed {
+        let mut lines: Vec<_> = entries
+            .iter()
+            .take(Self::MAX_LINES)
+            .map(|(entry, count)| match count {
+                1 => format!("{entry}"),
+                n => format!("{entry} *(x{n})*"),
+            })
+            .collect();
+
+        if entries.len() > Self::MAX_LINES {
+            lines.push(format!("*...and {} more*", entries.len() - Self::MAX_LINES));
+        }
+
+        let mut description = lines.join("\n");
+
+        if description.len() > 4000 {
+            let end = (0..=4000)
+                .rev()
+                .find(|i| description.is_char_boundary(*i))
+                .unwrap_or_default();
+
+            description.truncate(end);
+            description.push_str("...");
+        }
+
+        let embed = CreateEmbed::new()
+            .color(BOT_COLOR)
+            .description(description)
+            .timestamp(Timestamp::now())
+            .title("Warnings and Errors");
+
+        match suppressed {
+            0 => embed,
+            n => embed.footer(CreateEmbedFooter::new(format!(
+                "{n} repeated entries suppressed since the last post"
+            ))),
+        }
+    }
+
+    // posts one embed per guild that has a log channel configured, returning any failures
+    pub async fn flush(&self, http: &Http) -> Vec<(GuildId, Error)> {
+        let mut failed = vec![];
+
+        for (guild, (entries, suppressed)) in self.__take(Utc::now().timestamp_millis()) {
+            let Some(channel) = Settings::read(guild).ok().and_then(|s| s.log) else {
+                continue;
+            };
+            let message = CreateMessage::new().embed(Self::__embed(&entries, suppressed));
+
+            if let Err(error) = channel
+                .send_message(http, message)
+                .counted("create_message")
+                .await
+            {
+                failed.push((guild, error.into()));
+            }
+        }
+
+        failed
+    }
+}
@@ -0,0 +1,105 @@
+use crate::prelude::*;
+
+// previous and next buttons that carry the current page as the final custom id argument
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pager {
+    pub last: &'static str,
+    pub next: &'static str,
+}
+
+impl Pager {
+    pub const fn new(last: &'static str, next: &'static str) -> Self {
+        Self { last, next }
+    }
+
+    // pages are one-indexed, and stepping past either end wraps around
+    pub const fn wrap(page: usize, pages: usize) -> usize {
+        if page == 0 {
+            pages
+        } else if page > pages {
+            1
+        } else {
+            page
+        }
+    }
+
+    pub fn buttons(
+        self,
+        args: &[String],
+        page: usize,
+        pages: usize,
+        disabled: bool,
+    ) -> Vec<CreateButton> {
+        let page = Self::wrap(page, pages);
+        let disabled = disabled || pages <= 1;
+        let id = |name: &str| {
+            args.iter()
+                .fold(CustomId::new(name), |id, arg| id.arg(arg))
+                .arg(page)
+        };
+
+        let last = CreateButton::new(id(self.last))
+            .disabled(disabled)
+            .emoji('⬅')
+            .style(ButtonStyle::Secondary);
+        let next = CreateButton::new(id(self.next))
+            .disabled(disabled)
+            .emoji('➡')
+            .style(ButtonStyle::Secondary);
+
+        vec![last, next]
+    }
+
+    // returns the page that a pressed button points to, which may still need wrapping
+    pub fn step(self, custom_id: &CustomId) -> Result<usize> {
+        let Some(page) = custom_id.args.last() else {
+            return Err(Error::MissingId(Value::Data));
+        };
+        let Ok(page) = page.parse::<usize>() else {
+            return Err(Error::InvalidId(Value::Data, page.clone()));
+        };
+
+        if custom_id.name == self.last {
+            Ok(page.saturating_sub(1))
+        } else if custom_id.name == self.next {
+            Ok(page.saturating_add(1))
+        } else {
+            Err(Error::InvalidId(Value::Component, custom_id.name.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGER: Pager = Pager::new("test_last", "test_next");
+
+    #[test]
+    fn wrap_past_either_end() {
+        assert_eq!(Pager::wrap(0, 3), 3);
+        assert_eq!(Pager::wrap(1, 3), 1);
+        assert_eq!(Pager::wrap(3, 3), 3);
+        assert_eq!(Pager::wrap(4, 3), 1);
+        assert_eq!(Pager::wrap(0, 1), 1);
+    }
+
+    #[test]
+    fn step_from_buttons() -> Result<()> {
+        let last = CustomId::new(PAGER.last).arg("filter").arg(2);
+        let next = CustomId::new(PAGER.next).arg(3);
+
+        assert_eq!(PAGER.step(&last)?, 1);
+        assert_eq!(PAGER.step(&next)?, 4);
+        assert_eq!(PAGER.step(&CustomId::new(PAGER.last).arg(0))?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn step_rejects_invalid_ids() {
+        assert!(PAGER.step(&CustomId::new(PAGER.next)).is_err());
+        assert!(PAGER.step(&CustomId::new(PAGER.next).arg("page")).is_err());
+        assert!(PAGER.step(&CustomId::new("test_other").arg(1)).is_err());
+    }
+}
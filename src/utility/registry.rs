@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+// a slash command along with every interaction that it owns; components and modals are routed by
+// the base of their custom id, which must match the command's name
+#[async_trait]
+pub trait BotCommand: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn create(&self) -> CreateCommand;
+
+    fn cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+    fn component_cooldown(&self) -> Option<Cooldown> {
+        None
+    }
+    // whether a component spends the shared component cooldown
+    fn uses_cooldown(&self, _: &CustomId) -> bool {
+        true
+    }
+
+    async fn run_command(&self, _: &Http, cmd: &CommandInteraction) -> Result<()> {
+        Err(Error::InvalidValue(Value::Command, cmd.data.name.clone()))
+    }
+    async fn run_component(&self, _: &Http, cpn: &mut ComponentInteraction) -> Result<()> {
+        Err(Error::InvalidValue(
+            Value::Component,
+            cpn.data.custom_id.clone(),
+        ))
+    }
+    async fn run_modal(&self, _: &Http, mdl: &ModalInteraction) -> Result<()> {
+        Err(Error::InvalidValue(
+            Value::Modal,
+            mdl.data.custom_id.clone(),
+        ))
+    }
+    // given the full context, so that suggestions can be resolved from the cache
+    async fn run_autocomplete(&self, _: &Context, cmd: &CommandInteraction) -> Result<()> {
+        Err(Error::InvalidValue(Value::Command, cmd.data.name.clone()))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Registry(&'static [&'static dyn BotCommand]);
+
+impl Registry {
+    pub const fn new(commands: &'static [&'static dyn BotCommand]) -> Self {
+        Self(commands)
+    }
+
+    pub fn get(self, name: &str) -> Result<&'static dyn BotCommand> {
+        self.0
+            .iter()
+            .find(|c| c.name() == name)
+            .copied()
+            .ok_or_else(|| Error::InvalidValue(Value::Command, name.to_string()))
+    }
+    pub fn iter(self) -> impl Iterator<Item = &'static dyn BotCommand> {
+        self.0.iter().copied()
+    }
+    pub fn create(self) -> Vec<CreateCommand> {
+        self.iter().map(BotCommand::create).collect()
+    }
+
+    // compares the registry against the commands that discord reports, only pushing when a command
+    // was added, removed, or differs from its registered fields; the stored hash also catches edits
+    // to fields that discord does not echo back
+    pub async fn sync(self, http: &Http, scope: Scope) -> Result<Changes> {
+        let remote = match scope {
+            Scope::Global => {
+                http.get_global_application_commands()
+                    .counted("get_global_application_commands")
+                    .await?
+            }
+            Scope::Guild(guild) => {
+                http.get_guild_application_commands(guild)
+                    .counted("get_guild_application_commands")
+                    .await?
+            }
+        };
+        let req = Registration::new_req(scope);
+        let _lock = req.lock().await?;
+        let stored = match req.read() {
+            Ok(stored) => stored,
+            Err(error) if error.is_not_found() => Registration::default(),
+            Err(error) => return Err(error),
+        };
+
+        let commands = self.create();
+        let mut local = BTreeMap::new();
+        let mut fields = BTreeMap::new();
+
+        for (command, create) in self.iter().zip(&commands) {
+            let name = command.name().to_string();
+
+            local.insert(name.clone(), Registration::__hash(create)?);
+            fields.insert(name, Registration::__fields(&serde_json::to_value(create)?));
+        }
+
+        let mut registered = BTreeMap::new();
+
+        for command in &remote {
+            let value = Registration::__fields(&serde_json::to_value(command)?);
+
+            registered.insert(command.name.clone(), value);
+        }
+
+        let changes = Changes {
+            added: local
+                .keys()
+                .filter(|n| !registered.contains_key(*n))
+                .cloned()
+                .collect(),
+            removed: registered
+                .keys()
+                .filter(|n| !local.contains_key(*n))
+                .cloned()
+                .collect(),
+            changed: local
+                .iter()
+                .filter(|(n, h)| {
+                    registered
+                        .get(*n)
+                        .is_some_and(|r| fields.get(*n) != Some(r) || stored.0.get(*n) != Some(h))
+                })
+                .map(|(n, _)| n.clone())
+                .collect(),
+        };
+
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+
+        match scope {
+            Scope::Global => {
+                http.create_global_application_commands(&commands)
+                    .counted("create_global_application_commands")
+                    .await?
+            }
+            Scope::Guild(guild) => {
+                http.create_guild_application_commands(guild, &commands)
+                    .counted("create_guild_application_commands")
+                    .await?
+            }
+        };
+
+        req.write(&Registration(local))?;
+
+        Ok(changes)
+    }
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.iter().map(BotCommand::name))
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Global => write!(f, "Global"),
+            Self::Guild(guild) => write!(f, "Guild<{guild}>"),
+        }
+    }
+}
+
+// the definition hash of each command as it was last pushed to a scope
+#[repr(transparent)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Registration(pub BTreeMap<String, String>);
+
+impl Registration {
+    pub const DIR: &str = "registry";
+    // the fields that both a builder and a registered command carry
+    pub const FIELDS: [&str; 16] = [
+        "type",
+        "name",
+        "description",
+        "options",
+        "required",
+        "choices",
+        "value",
+        "channel_types",
+        "min_value",
+        "max_value",
+        "min_length",
+        "max_length",
+        "autocomplete",
+        "default_member_permissions",
+        "dm_permission",
+        "nsfw",
+    ];
+
+    fn __is_default(value: &serde_json::Value) -> bool {
+        match value {
+            serde_json::Value::Null | serde_json::Value::Bool(false) => true,
+            serde_json::Value::Array(list) => list.is_empty(),
+            serde_json::Value::Object(map) => map.is_empty(),
+            _ => false,
+        }
+    }
+    // keeps only the shared fields, dropping any that are unset or left at discord's default
+    fn __normalize(value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .filter(|(k, _)| Self::FIELDS.contains(&k.as_str()))
+                    .map(|(k, v)| (k.clone(), Self::__normalize(v)))
+                    .filter(|(_, v)| !Self::__is_default(v))
+                    .collect(),
+            ),
+            serde_json::Value::Array(list) => {
+                serde_json::Value::Array(list.iter().map(Self::__normalize).collect())
+            }
+            value => value.clone(),
+        }
+    }
+    // commands default to chat input and to being usable in direct messages
+    fn __fields(value: &serde_json::Value) -> serde_json::Value {
+        let mut value = Self::__normalize(value);
+
+        if let Some(map) = value.as_object_mut() {
+            if map.get("type").and_then(serde_json::Value::as_u64) == Some(1) {
+                map.remove("type");
+            }
+            if map.get("dm_permission") == Some(&serde_json::Value::Bool(true)) {
+                map.remove("dm_permission");
+            }
+        }
+
+        value
+    }
+
+    // fnv-1a, since the result is persisted and must not change between builds
+    fn __hash(command: &CreateCommand) -> Result<String> {
+        let json = serde_json::to_string(command)?;
+        let hash = json.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        Ok(format!("{hash:016x}"))
+    }
+}
+
+impl Versioned for Registration {}
+
+impl NewReq<Scope> for Registration {
+    fn new_req(scope: Scope) -> Req<Self> {
+        match scope {
+            Scope::Global => Req::new(Self::DIR, "global"),
+            Scope::Guild(guild) => Req::new(Self::DIR, guild),
+        }
+    }
+}
+
+impl NewReqDir<()> for Registration {
+    fn new_req_dir(_: ()) -> ReqDir<Self> {
+        ReqDir::new(Self::DIR)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "added [{}], removed [{}], changed [{}]",
+            self.added.join(", "),
+            self.removed.join(", "),
+            self.changed.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn __local() -> Result<serde_json::Value> {
+        let option =
+            CreateCommandOption::new(CommandOptionType::String, "text", "Some text").required(true);
+        let command = CreateCommand::new("test")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .description("A test command")
+            .add_option(option);
+
+        Ok(Registration::__fields(&serde_json::to_value(command)?))
+    }
+
+    #[test]
+    fn fields_match_registered_command() -> Result<()> {
+        let remote = serde_json::json!({
+            "id": "1",
+            "application_id": "2",
+            "version": "3",
+            "type": 1,
+            "name": "test",
+            "name_localizations": null,
+            "description": "A test command",
+            "default_member_permissions": Permissions::MANAGE_GUILD.bits().to_string(),
+            "dm_permission": true,
+            "nsfw": false,
+            "options": [{
+                "type": 3,
+                "name": "text",
+                "description": "Some text",
+                "required": true,
+                "autocomplete": false
+            }]
+        });
+
+        assert_eq!(__local()?, Registration::__fields(&remote));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fields_differ_on_remote_edit() -> Result<()> {
+        let remote = serde_json::json!({
+            "type": 1,
+            "name": "test",
+            "description": "An outdated description",
+            "default_member_permissions": Permissions::MANAGE_GUILD.bits().to_string(),
+            "options": [{ "type": 3, "name": "text", "description": "Some text", "required": true }]
+        });
+
+        assert_ne!(__local()?, Registration::__fields(&remote));
+
+        Ok(())
+    }
+}
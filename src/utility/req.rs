@@ -1,15 +1,17 @@
-use std::{
-    fs::{create_dir_all, remove_file, File},
-    io::Write,
-    path::PathBuf,
-};
+use std::{cmp::Ordering, collections::BTreeMap, sync::Mutex};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
 
 use crate::prelude::*;
 
+static LOCKS: Mutex<BTreeMap<String, Arc<AsyncMutex<()>>>> = Mutex::new(BTreeMap::new());
+
+pub type ReqLock = OwnedMutexGuard<()>;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Req<T>
 where
-    T: Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    T: Send + Sync + Versioned,
 {
     dir: String,
     key: String,
@@ -18,11 +20,8 @@ where
 
 impl<T> Req<T>
 where
-    T: Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    T: Send + Sync + Versioned,
 {
-    pub const DIR: &str = "data";
-    pub const EXT: &str = "rmp";
-
     #[allow(clippy::needless_pass_by_value)]
     pub fn new(dir: impl ToString, key: impl ToString) -> Self {
         Self {
@@ -32,34 +31,257 @@ where
         }
     }
 
-    pub fn dir(&self) -> PathBuf {
-        PathBuf::from(Self::DIR).join(&self.dir)
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.dir, self.key)
+    }
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    // serializes read-modify-write cycles on the same record; hold the guard until written
+    pub async fn lock(&self) -> Result<ReqLock> {
+        let lock = {
+            let Ok(mut locks) = LOCKS.lock() else {
+                return Err(Error::Other("The record lock table has been poisoned"));
+            };
+
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+
+            Arc::clone(locks.entry(self.id()).or_default())
+        };
+
+        Ok(lock.lock_owned().await)
     }
-    pub fn path(&self) -> PathBuf {
-        self.dir().join(&self.key).with_extension(Self::EXT)
+    // the same lock for code on a blocking thread, which must not be called from an async task
+    pub fn blocking_lock(&self) -> Result<ReqLock> {
+        tokio::runtime::Handle::current().block_on(self.lock())
     }
 
     pub fn read(&self) -> Result<T> {
-        let file = File::open(self.path())?;
+        let start = std::time::Instant::now();
+        let result = self.__read();
+
+        metrics().storage("read", start.elapsed());
 
-        Ok(rmp_serde::from_read(file)?)
+        result
+    }
+    fn __read(&self) -> Result<T> {
+        let raw = storage().read(&self.dir, &self.key)?;
+        let error = match Envelope::decode(&raw) {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        let Ok(raw) = storage().backup(&self.dir, &self.key) else {
+            return Err(error);
+        };
+        let Ok(value) = Envelope::decode(&raw) else {
+            return Err(error);
+        };
+
+        storage().restore(&self.dir, &self.key)?;
+
+        Ok(value)
     }
     pub fn write(&self, value: &T) -> Result<()> {
-        create_dir_all(self.dir())?;
+        let start = std::time::Instant::now();
+        let result = Envelope::new(value)
+            .seal()
+            .and_then(|raw| storage().write(&self.dir, &self.key, &raw));
 
-        let raw = rmp_serde::to_vec(value)?;
-        let mut file = File::create(self.path())?;
+        metrics().storage("write", start.elapsed());
 
-        file.write_all(&raw).map_err(Error::from)
+        result
     }
     pub fn remove(&self) -> Result<()> {
-        remove_file(self.path()).map_err(Error::from)
+        storage().remove(&self.dir, &self.key)
+    }
+    pub fn migrate(&self) -> Result<bool> {
+        let raw = storage().read(&self.dir, &self.key)?;
+        let (version, _) = Envelope::<T>::open(&raw)?;
+
+        if version == T::version() && Format::detect(&raw) == storage_format() {
+            return Ok(false);
+        }
+
+        self.write(&self.read()?)?;
+
+        Ok(true)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReqDir<T>
+where
+    T: Send + Sync + Versioned,
+{
+    dir: String,
+    _marker: PhantomData<T>,
+}
+
+impl<T> ReqDir<T>
+where
+    T: Send + Sync + Versioned,
+{
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn new(dir: impl ToString) -> Self {
+        Self {
+            dir: dir.to_string(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn req(&self, key: impl ToString) -> Req<T> {
+        Req::new(&self.dir, key)
+    }
+    pub fn child(&self, name: impl Display) -> Self {
+        Self::new(format!("{}/{name}", self.dir))
+    }
+
+    pub fn reqs(&self) -> Result<Vec<Req<T>>> {
+        let keys = storage().keys(&self.dir)?;
+
+        Ok(keys.into_iter().map(|k| self.req(k)).collect())
+    }
+    pub fn dirs(&self) -> Result<Vec<Self>> {
+        let dirs = storage().dirs(&self.dir)?;
+
+        Ok(dirs.into_iter().map(|d| self.child(d)).collect())
+    }
+
+    pub fn query(&self) -> Query<T> {
+        Query::new(Self::new(&self.dir))
+    }
+}
+
+type Filter<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+type Sorter<T> = Box<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+// collects the typed records of a directory, optionally including every subdirectory
+pub struct Query<T>
+where
+    T: Send + Sync + Versioned,
+{
+    dir: ReqDir<T>,
+    deep: bool,
+    exclude: Vec<String>,
+    filters: Vec<Filter<T>>,
+    sorter: Option<Sorter<T>>,
+    limit: Option<usize>,
+}
+
+impl<T> Query<T>
+where
+    T: Send + Sync + Versioned,
+{
+    pub const fn new(dir: ReqDir<T>) -> Self {
+        Self {
+            dir,
+            deep: false,
+            exclude: vec![],
+            filters: vec![],
+            sorter: None,
+            limit: None,
+        }
+    }
+
+    pub const fn deep(mut self) -> Self {
+        self.deep = true;
+        self
+    }
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn exclude(mut self, key: impl ToString) -> Self {
+        self.exclude.push(key.to_string());
+        self
+    }
+    pub fn filter(mut self, f: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.filters.push(Box::new(f));
+        self
+    }
+    pub fn sort_by_key<K: Ord>(mut self, f: impl Fn(&T) -> K + Send + Sync + 'static) -> Self {
+        self.sorter = Some(Box::new(move |a, b| f(a).cmp(&f(b))));
+        self
+    }
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn __reqs(&self, dir: &ReqDir<T>, reqs: &mut Vec<Req<T>>) -> Result<()> {
+        for req in dir.reqs()? {
+            if !self.exclude.contains(&req.key) {
+                reqs.push(req);
+            }
+        }
+
+        if self.deep {
+            for dir in dir.dirs()? {
+                self.__reqs(&dir, reqs)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // every matching key, without reading or filtering the stored values
+    pub fn reqs(&self) -> Result<Vec<Req<T>>> {
+        let mut reqs = vec![];
+
+        self.__reqs(&self.dir, &mut reqs)?;
+
+        Ok(reqs)
+    }
+    // records that fail to read are skipped so that one bad file cannot hide the rest
+    pub fn read(&self) -> Result<Vec<(Req<T>, T)>> {
+        self.read_with_errors().map(|(entries, _)| entries)
+    }
+    // like `read`, but also returns every record that exists and could not be read
+    #[allow(clippy::type_complexity)]
+    pub fn read_with_errors(&self) -> Result<(Vec<(Req<T>, T)>, Vec<(Req<T>, Error)>)> {
+        let mut entries = vec![];
+        let mut failed = vec![];
+
+        for req in self.reqs()? {
+            let value = match req.read() {
+                Ok(value) => value,
+                Err(error) if error.is_not_found() => continue,
+                Err(error) => {
+                    failed.push((req, error));
+                    continue;
+                }
+            };
+
+            if self.filters.iter().all(|f| f(&value)) {
+                entries.push((req, value));
+            }
+        }
+
+        if let Some(sorter) = &self.sorter {
+            entries.sort_by(|(_, a), (_, b)| sorter(a, b));
+        }
+        if let Some(limit) = self.limit {
+            entries.truncate(limit);
+        }
+
+        Ok((entries, failed))
+    }
+}
+
+pub trait NewReqDir<T>
+where
+    Self: Send + Sync + Versioned,
+    T: Send + Sync,
+{
+    fn new_req_dir(_: T) -> ReqDir<Self>;
+
+    fn query(value: T) -> Query<Self> {
+        Self::new_req_dir(value).query()
     }
 }
 
 pub trait TryNewReq<T>
 where
-    Self: Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    Self: Send + Sync + Versioned,
     T: Send + Sync,
 {
     fn try_new_req(_: T) -> Result<Req<Self>>;
@@ -71,7 +293,7 @@ where
 
 pub trait NewReq<T>
 where
-    Self: Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    Self: Send + Sync + Versioned,
     T: Send + Sync,
 {
     fn new_req(_: T) -> Req<Self>;
@@ -93,7 +315,7 @@ where
 
 pub trait TryAsReq<T>
 where
-    Self: Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    Self: Send + Sync + Versioned,
     T: Send + Sync,
 {
     fn try_as_req(&self, _: T) -> Result<Req<Self>>;
@@ -108,7 +330,7 @@ where
 
 pub trait AsReq<T>
 where
-    Self: Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    Self: Send + Sync + Versioned,
 {
     fn as_req(&self, _: T) -> Req<Self>;
 
@@ -0,0 +1,139 @@
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::prelude::*;
+
+// a minimal local http server for operational endpoints; it only understands `GET <path>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Server {
+    pub addr: SocketAddr,
+}
+
+impl Server {
+    pub const KEY: &str = "SERVER_ADDR";
+    pub const DEFAULT_ADDR: &str = "127.0.0.1:9184";
+    pub const MAX_REQUEST: usize = 8 * 1024;
+    pub const ACCEPT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(100);
+
+    // returns `None` when the server has been disabled with an address of `off`
+    pub fn from_env() -> Result<Option<Self>> {
+        let raw = std::env::var(Self::KEY).unwrap_or_else(|_| Self::DEFAULT_ADDR.to_string());
+
+        if raw.eq_ignore_ascii_case("off") {
+            return Ok(None);
+        }
+
+        let addr = raw
+            .parse()
+            .map_err(|_| Error::InvalidValue(Value::Other("Server address"), raw))?;
+
+        Ok(Some(Self { addr }))
+    }
+
+    pub async fn run(self, logger: Logger) -> Result<()> {
+        let listener = TcpListener::bind(self.addr).await?;
+
+        logger.info(format!("Listening: http://{}", self.addr))?;
+
+        loop {
+            // accept errors such as running out of descriptors are transient, so keep serving
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    logger.warn(format!("Accept failed: {error}"))?;
+                    tokio::time::sleep(Self::ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let logger = logger.clone();
+
+            tokio::spawn(async move {
+                if let Err(error) = Self::__handle(stream).await {
+                    logger.debug(format!("Request failed: {error}")).ok();
+                }
+            });
+        }
+    }
+
+    async fn __handle(mut stream: TcpStream) -> Result<()> {
+        let mut buffer = vec![0; Self::MAX_REQUEST];
+        let mut length = 0;
+
+        // only the request line matters, so stop reading once the headers are complete
+        while !buffer[..length].windows(4).any(|w| w == b"\r\n\r\n") {
+            if length == buffer.len() {
+                return Self::__respond(&mut stream, 431, "text/plain", "Request too large").await;
+            }
+
+            match stream.read(&mut buffer[length..]).await? {
+                0 => break,
+                n => length += n,
+            }
+        }
+
+        let request = String::from_utf8_lossy(&buffer[..length]);
+        let mut parts = request
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or_default();
+        let path = path.split('?').next().unwrap_or_default();
+
+        if method != "GET" {
+            return Self::__respond(&mut stream, 405, "text/plain", "Method not allowed").await;
+        }
+
+        match path {
+            "/healthz" => {
+                let body = health().liveness().to_string();
+
+                Self::__respond(&mut stream, 200, "application/json", &body).await
+            }
+            "/readyz" => {
+                let (ready, details) = health().readiness()?;
+                let status = if ready { 200 } else { 503 };
+
+                Self::__respond(
+                    &mut stream,
+                    status,
+                    "application/json",
+                    &details.to_string(),
+                )
+                .await
+            }
+            "/metrics" => {
+                let body = metrics().render()?;
+
+                Self::__respond(&mut stream, 200, "text/plain; version=0.0.4", &body).await
+            }
+            _ => Self::__respond(&mut stream, 404, "text/plain", "Not found").await,
+        }
+    }
+
+    async fn __respond(stream: &mut TcpStream, status: u16, kind: &str, body: &str) -> Result<()> {
+        let reason = match status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        };
+        let head = format!(
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: {kind}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+        stream.shutdown().await?;
+
+        Ok(())
+    }
+}
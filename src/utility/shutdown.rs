@@ -0,0 +1,131 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{watch, Notify},
+    time::Interval,
+};
+
+use crate::prelude::*;
+
+static SHUTDOWN: OnceLock<Shutdown> = OnceLock::new();
+
+// tracks in-flight work so that the process can wait for it to finish before exiting
+#[derive(Debug)]
+pub struct Shutdown {
+    closing: watch::Sender<bool>,
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+impl Shutdown {
+    pub const KEY: &str = "SHUTDOWN_TIMEOUT_SECS";
+    pub const DEFAULT_SECS: u64 = 30;
+
+    pub fn timeout() -> Result<Duration> {
+        env_var(Self::KEY, Self::DEFAULT_SECS).map(Duration::from_secs)
+    }
+
+    pub fn is_closing(&self) -> bool {
+        *self.closing.borrow()
+    }
+    pub fn begin(&self) {
+        self.closing.send_replace(true);
+    }
+    pub async fn closed(&self) {
+        let mut closing = self.closing.subscribe();
+
+        while !*closing.borrow_and_update() {
+            if closing.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+
+    // returns `None` once shutdown has begun, so that no new work is started
+    pub fn task(&'static self) -> Option<TaskGuard> {
+        // counting first means that `drain` either sees this task or the task sees the shutdown
+        self.active.fetch_add(1, Ordering::SeqCst);
+
+        let guard = TaskGuard(self);
+
+        if self.is_closing() {
+            None
+        } else {
+            Some(guard)
+        }
+    }
+    // waits for the next tick, unless shutdown begins first
+    pub async fn tick(&'static self, interval: &mut Interval) -> Option<TaskGuard> {
+        tokio::select! {
+            _ = interval.tick() => self.task(),
+            () = self.closed() => None,
+        }
+    }
+
+    // returns whether every task finished before the timeout
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        let drained = async {
+            loop {
+                let idle = self.idle.notified();
+
+                if self.active.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+
+                idle.await;
+            }
+        };
+
+        tokio::time::timeout(timeout, drained).await.is_ok()
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            closing: watch::channel(false).0,
+            active: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TaskGuard(&'static Shutdown);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if self.0.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+pub fn shutdown() -> &'static Shutdown {
+    SHUTDOWN.get_or_init(Shutdown::default)
+}
+
+// resolves on ctrl-c, or on sigterm where it exists
+pub async fn signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map_err(Error::from),
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map_err(Error::from)
+    }
+}
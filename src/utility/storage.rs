@@ -0,0 +1,530 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{copy, create_dir_all, hard_link, read_dir, remove_dir_all, remove_file, rename, File},
+    io::{ErrorKind, Read, Write},
+    path::PathBuf,
+    sync::{Mutex, OnceLock, RwLock},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::prelude::*;
+
+static STORAGE: OnceLock<Box<dyn Storage>> = OnceLock::new();
+
+fn __not_found() -> Error {
+    Error::ReadWrite(std::io::Error::from(ErrorKind::NotFound))
+}
+fn __poisoned<T>(_: T) -> Error {
+    Error::Other("The storage lock has been poisoned")
+}
+// returns the name of the directory directly below `dir` that contains `path`
+fn __child(dir: &str, path: &str) -> Option<String> {
+    let rest = if dir.is_empty() {
+        path
+    } else {
+        path.strip_prefix(dir)?.strip_prefix('/')?
+    };
+
+    rest.split('/')
+        .next()
+        .filter(|s| !s.is_empty())
+        .map(ToString::to_string)
+}
+
+pub trait Storage: Send + Sync + std::fmt::Debug {
+    fn read(&self, dir: &str, key: &str) -> Result<Vec<u8>>;
+    fn write(&self, dir: &str, key: &str, raw: &[u8]) -> Result<()>;
+    fn remove(&self, dir: &str, key: &str) -> Result<()>;
+    fn keys(&self, dir: &str) -> Result<Vec<String>>;
+    fn dirs(&self, dir: &str) -> Result<Vec<String>>;
+
+    fn backup(&self, _dir: &str, _key: &str) -> Result<Vec<u8>> {
+        Err(__not_found())
+    }
+    fn restore(&self, _dir: &str, _key: &str) -> Result<()> {
+        Err(__not_found())
+    }
+    // checks that records can currently be written, without touching any of them
+    fn probe(&self) -> Result<()> {
+        Ok(())
+    }
+    // removes every record within `dir` and its subdirectories
+    fn clear(&self, dir: &str) -> Result<()> {
+        for key in self.keys(dir)? {
+            self.remove(dir, &key)?;
+        }
+        for child in self.dirs(dir)? {
+            if dir.is_empty() {
+                self.clear(&child)?;
+            } else {
+                self.clear(&format!("{dir}/{child}"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageKind {
+    #[default]
+    File,
+    Sqlite,
+    Memory,
+}
+
+impl StorageKind {
+    pub const KEY: &str = "STORAGE";
+
+    pub fn from_env() -> Result<Self> {
+        std::env::var(Self::KEY).map_or_else(|_| Ok(Self::default()), Self::try_from)
+    }
+
+    pub fn create(self, format: Format) -> Result<Box<dyn Storage>> {
+        Ok(match self {
+            Self::File => Box::new(FileStorage::new(FileStorage::DIR, format)),
+            Self::Sqlite => Box::new(SqliteStorage::open(SqliteStorage::PATH)?),
+            Self::Memory => Box::<MemoryStorage>::default(),
+        })
+    }
+}
+
+impl TryFrom<String> for StorageKind {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "file" | "rmp" => Ok(Self::File),
+            "sqlite" | "db" => Ok(Self::Sqlite),
+            "memory" | "mem" => Ok(Self::Memory),
+            _ => Err(Error::InvalidValue(Value::Other("Storage"), value)),
+        }
+    }
+}
+
+pub fn init_storage(kind: StorageKind, format: Format) -> Result<()> {
+    init_format(format)?;
+
+    STORAGE
+        .set(kind.create(format)?)
+        .map_err(|_| Error::Other("Storage has already been initialized"))
+}
+pub fn storage() -> &'static dyn Storage {
+    STORAGE
+        .get_or_init(|| Box::new(FileStorage::new(FileStorage::DIR, storage_format())))
+        .as_ref()
+}
+
+#[derive(Clone, Debug)]
+pub struct FileStorage {
+    root: PathBuf,
+    format: Format,
+}
+
+impl FileStorage {
+    pub const DIR: &str = "data";
+    pub const EXT_BACKUP: &str = "bak";
+    pub const EXT_CORRUPT: &str = "bad";
+    pub const EXT_TEMP: &str = "tmp";
+    pub const PROBE: &str = ".probe";
+
+    pub fn new(root: impl Into<PathBuf>, format: Format) -> Self {
+        let root = root.into();
+
+        Self { root, format }
+    }
+
+    pub fn dir(&self, dir: &str) -> PathBuf {
+        self.root.join(dir)
+    }
+    pub fn path(&self, dir: &str, key: &str) -> PathBuf {
+        self.dir(dir).join(key).with_extension(self.format.ext())
+    }
+
+    // writes to a temporary file first so a crash never leaves a truncated record behind
+    fn __replace(&self, dir: &str, key: &str, raw: &[u8], backup: bool) -> Result<()> {
+        let path = self.path(dir, key);
+        let temp = path.with_extension(Self::EXT_TEMP);
+
+        create_dir_all(self.dir(dir))?;
+
+        let mut file = File::create(&temp)?;
+
+        file.write_all(raw)?;
+        file.sync_all()?;
+
+        if backup && path.exists() {
+            let copy_path = path.with_extension(Self::EXT_BACKUP);
+
+            remove_file(&copy_path).ok();
+            hard_link(&path, &copy_path).or_else(|_| copy(&path, &copy_path).map(|_| ()))?;
+        }
+
+        rename(&temp, &path)?;
+
+        // not every platform allows syncing directories, so this is best-effort
+        if let Ok(dir) = File::open(self.dir(dir)) {
+            dir.sync_all().ok();
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, dir: &str, key: &str) -> Result<Vec<u8>> {
+        let mut file = File::open(self.path(dir, key))?;
+        let mut raw = vec![];
+
+        file.read_to_end(&mut raw)?;
+
+        Ok(raw)
+    }
+    fn write(&self, dir: &str, key: &str, raw: &[u8]) -> Result<()> {
+        self.__replace(dir, key, raw, true)
+    }
+    fn remove(&self, dir: &str, key: &str) -> Result<()> {
+        let path = self.path(dir, key);
+
+        remove_file(path.with_extension(Self::EXT_BACKUP)).ok();
+        remove_file(path).map_err(Error::from)
+    }
+    fn keys(&self, dir: &str) -> Result<Vec<String>> {
+        let entries = match read_dir(self.dir(dir)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+        let mut keys = vec![];
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if !path.is_file() || path.extension().map_or(true, |e| e != self.format.ext()) {
+                continue;
+            }
+            if let Some(key) = path.file_stem().and_then(|s| s.to_str()) {
+                keys.push(key.to_string());
+            }
+        }
+
+        keys.sort();
+
+        Ok(keys)
+    }
+    fn dirs(&self, dir: &str) -> Result<Vec<String>> {
+        let entries = match read_dir(self.dir(dir)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+        let mut dirs = vec![];
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if !path.is_dir() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|s| s.to_str()) {
+                dirs.push(name.to_string());
+            }
+        }
+
+        dirs.sort();
+
+        Ok(dirs)
+    }
+
+    fn backup(&self, dir: &str, key: &str) -> Result<Vec<u8>> {
+        let path = self.path(dir, key).with_extension(Self::EXT_BACKUP);
+        let mut file = File::open(path)?;
+        let mut raw = vec![];
+
+        file.read_to_end(&mut raw)?;
+
+        Ok(raw)
+    }
+    fn restore(&self, dir: &str, key: &str) -> Result<()> {
+        let raw = self.backup(dir, key)?;
+        let path = self.path(dir, key);
+
+        if path.exists() {
+            rename(&path, path.with_extension(Self::EXT_CORRUPT))?;
+        }
+
+        self.__replace(dir, key, &raw, false)
+    }
+    fn clear(&self, dir: &str) -> Result<()> {
+        match remove_dir_all(self.dir(dir)) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+    // the probe has no extension, so it is never listed as a record
+    fn probe(&self) -> Result<()> {
+        let path = self.root.join(Self::PROBE);
+
+        create_dir_all(&self.root)?;
+        File::create(&path)?.write_all(b"ok")?;
+        remove_file(path)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub const PATH: &str = "data.db";
+
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let conn = Connection::open(path.into())?;
+
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS records (
+                dir TEXT NOT NULL,
+                key TEXT NOT NULL,
+                raw BLOB NOT NULL,
+                PRIMARY KEY (dir, key)
+            )",
+            [],
+        )?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read(&self, dir: &str, key: &str) -> Result<Vec<u8>> {
+        let conn = self.conn.lock().map_err(__poisoned)?;
+
+        conn.query_row(
+            "SELECT raw FROM records WHERE dir = ?1 AND key = ?2",
+            params![dir, key],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(__not_found)
+    }
+    fn write(&self, dir: &str, key: &str, raw: &[u8]) -> Result<()> {
+        let conn = self.conn.lock().map_err(__poisoned)?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO records (dir, key, raw) VALUES (?1, ?2, ?3)",
+            params![dir, key, raw],
+        )?;
+
+        Ok(())
+    }
+    fn remove(&self, dir: &str, key: &str) -> Result<()> {
+        let conn = self.conn.lock().map_err(__poisoned)?;
+        let count = conn.execute(
+            "DELETE FROM records WHERE dir = ?1 AND key = ?2",
+            params![dir, key],
+        )?;
+
+        if count == 0 {
+            Err(__not_found())
+        } else {
+            Ok(())
+        }
+    }
+    fn keys(&self, dir: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().map_err(__poisoned)?;
+        let mut statement = conn.prepare("SELECT key FROM records WHERE dir = ?1 ORDER BY key")?;
+        let keys = statement
+            .query_map(params![dir], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(keys)
+    }
+    fn dirs(&self, dir: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().map_err(__poisoned)?;
+        let mut statement = conn.prepare("SELECT DISTINCT dir FROM records")?;
+        let mut dirs = BTreeSet::new();
+
+        for path in statement.query_map([], |row| row.get::<_, String>(0))? {
+            if let Some(child) = __child(dir, &path?) {
+                dirs.insert(child);
+            }
+        }
+
+        Ok(dirs.into_iter().collect())
+    }
+    fn probe(&self) -> Result<()> {
+        let conn = self.conn.lock().map_err(__poisoned)?;
+
+        // taking the write lock fails when the database is read-only or held elsewhere
+        conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;")?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    records: RwLock<BTreeMap<(String, String), Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, dir: &str, key: &str) -> Result<Vec<u8>> {
+        let records = self.records.read().map_err(__poisoned)?;
+
+        records
+            .get(&(dir.to_string(), key.to_string()))
+            .cloned()
+            .ok_or_else(__not_found)
+    }
+    fn write(&self, dir: &str, key: &str, raw: &[u8]) -> Result<()> {
+        let mut records = self.records.write().map_err(__poisoned)?;
+
+        records.insert((dir.to_string(), key.to_string()), raw.to_vec());
+
+        Ok(())
+    }
+    fn remove(&self, dir: &str, key: &str) -> Result<()> {
+        let mut records = self.records.write().map_err(__poisoned)?;
+
+        records
+            .remove(&(dir.to_string(), key.to_string()))
+            .map(|_| ())
+            .ok_or_else(__not_found)
+    }
+    fn keys(&self, dir: &str) -> Result<Vec<String>> {
+        let records = self.records.read().map_err(__poisoned)?;
+
+        Ok(records
+            .keys()
+            .filter(|(d, _)| d == dir)
+            .map(|(_, k)| k.clone())
+            .collect())
+    }
+    fn dirs(&self, dir: &str) -> Result<Vec<String>> {
+        let records = self.records.read().map_err(__poisoned)?;
+        let dirs: BTreeSet<_> = records
+            .keys()
+            .filter_map(|(d, _)| __child(dir, d))
+            .collect();
+
+        Ok(dirs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_round_trip() -> Result<()> {
+        let storage = MemoryStorage::default();
+
+        storage.write("poll/1", "2", b"first")?;
+        storage.write("poll/1", "2", b"second")?;
+
+        assert_eq!(storage.read("poll/1", "2")?, b"second");
+        assert!(storage.read("poll/1", "3").is_err_and(|e| e.is_not_found()));
+
+        storage.remove("poll/1", "2")?;
+
+        assert!(storage.read("poll/1", "2").is_err_and(|e| e.is_not_found()));
+        assert!(storage
+            .remove("poll/1", "2")
+            .is_err_and(|e| e.is_not_found()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_lists_keys_and_dirs() -> Result<()> {
+        let storage = MemoryStorage::default();
+
+        storage.write("poll", ".dat", b"")?;
+        storage.write("poll/1", "2", b"")?;
+        storage.write("poll/1/2", "3", b"")?;
+        storage.write("poll/4", "5", b"")?;
+        storage.write("role/1", "2", b"")?;
+
+        assert_eq!(storage.keys("poll")?, [".dat"]);
+        assert_eq!(storage.keys("poll/1")?, ["2"]);
+        assert_eq!(storage.dirs("poll")?, ["1", "4"]);
+        assert_eq!(storage.dirs("poll/1")?, ["2"]);
+        assert_eq!(storage.dirs("")?, ["poll", "role"]);
+        assert!(storage.keys("missing")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_clear() -> Result<()> {
+        let storage = MemoryStorage::default();
+
+        storage.write("poll/1", "2", b"")?;
+        storage.write("poll/1/2", "3", b"")?;
+        storage.write("poll/10", "2", b"")?;
+        storage.clear("poll/1")?;
+
+        assert!(storage.dirs("poll/1")?.is_empty());
+        assert!(storage.keys("poll/1")?.is_empty());
+        assert_eq!(storage.keys("poll/10")?, ["2"]);
+
+        Ok(())
+    }
+
+    // each test gets its own directory, since tests run in parallel
+    fn file_storage(name: &str) -> FileStorage {
+        let root = std::env::temp_dir().join(format!("mrm_{name}_{}", std::process::id()));
+
+        remove_dir_all(&root).ok();
+
+        FileStorage::new(root, Format::Json)
+    }
+
+    #[test]
+    fn file_restores_backup_over_corrupt_record() -> Result<()> {
+        let storage = file_storage("restore");
+
+        storage.write("poll/1", "2", b"{\"valid\":true}")?;
+        storage.write("poll/1", "2", b"{\"val")?;
+
+        assert_eq!(storage.backup("poll/1", "2")?, b"{\"valid\":true}");
+
+        storage.restore("poll/1", "2")?;
+
+        let corrupt = storage
+            .path("poll/1", "2")
+            .with_extension(FileStorage::EXT_CORRUPT);
+
+        assert_eq!(storage.read("poll/1", "2")?, b"{\"valid\":true}");
+        assert_eq!(std::fs::read(corrupt)?, b"{\"val");
+
+        storage.clear("")
+    }
+
+    #[test]
+    fn file_restore_needs_a_backup() -> Result<()> {
+        let storage = file_storage("no_backup");
+
+        storage.write("poll/1", "2", b"{\"val")?;
+
+        assert!(storage
+            .restore("poll/1", "2")
+            .is_err_and(|e| e.is_not_found()));
+        assert_eq!(storage.read("poll/1", "2")?, b"{\"val");
+
+        storage.remove("poll/1", "2")?;
+
+        assert!(storage
+            .backup("poll/1", "2")
+            .is_err_and(|e| e.is_not_found()));
+
+        storage.clear("")
+    }
+}